@@ -0,0 +1,244 @@
+//! Pluggable rendering backends
+//!
+//! `MasonryHandler` only talks to the renderer through [`RenderBackend`], so the
+//! wgpu/Vello [`RenderContext`](crate::render::RenderContext) can be swapped for
+//! CPU renderers, or for [`RecordingRenderer`] in tests that run without a GPU.
+
+use crate::render::{RenderError, RenderOptions};
+use baseview::Window;
+use raw_window_handle::{
+    HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle,
+};
+use std::sync::{Arc, Mutex};
+use vello::kurbo::Rect;
+use vello::peniko::Color;
+use vello::Scene;
+
+/// A renderer that turns masonry's scenes into pixels
+///
+/// Backends are `Send` so they can be moved onto a dedicated render thread.
+pub trait RenderBackend: Send {
+    /// Resize the rendering target (physical pixels)
+    fn resize(&mut self, width: u32, height: u32);
+
+    /// Render a scene on top of `base_color` and present it
+    fn render(&mut self, scene: &Scene, base_color: Color) -> Result<(), RenderError>;
+
+    /// Render a scene where only `damage` (physical pixels) changed since the last frame
+    ///
+    /// Backends that can't keep the previous frame render everything.
+    fn render_damaged(
+        &mut self,
+        scene: &Scene,
+        base_color: Color,
+        damage: Rect,
+    ) -> Result<(), RenderError> {
+        let _ = damage;
+        self.render(scene, base_color)
+    }
+
+    /// The window was hidden, release whatever memory can be recreated later
+    fn suspend(&mut self) {}
+
+    /// The window is visible again, the next frame will be rendered in full
+    fn resume(&mut self) {}
+
+    /// Render a scene offscreen and return the resulting pixels
+    fn read_pixels(&mut self, scene: &Scene, base_color: Color)
+        -> Result<PixelBuffer, RenderError>;
+}
+
+/// Creates a backend for a freshly opened window
+///
+/// Called on the window thread with the window's handles and physical size.
+/// The window outlives the returned backend.
+pub type BackendFactory = Arc<
+    dyn Fn(&WindowTarget, u32, u32, &RenderOptions) -> Result<Box<dyn RenderBackend>, RenderError>
+        + Send
+        + Sync,
+>;
+
+/// Raw handles of the window a backend draws into
+///
+/// Factories only get the handles, so backends that don't present anything
+/// can be created without opening a window.
+#[derive(Clone, Copy, Debug)]
+pub struct WindowTarget {
+    window: RawWindowHandle,
+    display: RawDisplayHandle,
+}
+
+impl WindowTarget {
+    pub fn new(window: RawWindowHandle, display: RawDisplayHandle) -> Self {
+        Self { window, display }
+    }
+
+    /// The handles of a baseview window
+    pub fn of(window: &Window) -> Self {
+        Self::new(window.raw_window_handle(), window.raw_display_handle())
+    }
+}
+
+unsafe impl HasRawWindowHandle for WindowTarget {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        self.window
+    }
+}
+
+unsafe impl HasRawDisplayHandle for WindowTarget {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        self.display
+    }
+}
+
+/// RGBA8 pixels read back from a backend
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PixelBuffer {
+    pub width: u32,
+    pub height: u32,
+    /// Tightly packed rows of straight-alpha RGBA8 pixels
+    pub data: Vec<u8>,
+}
+
+impl PixelBuffer {
+    /// Get the RGBA value of a pixel, or None if out of bounds
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let i = ((y * self.width + x) * 4) as usize;
+        self.data[i..i + 4].try_into().ok()
+    }
+}
+
+/// A scene submitted to a [`RecordingRenderer`]
+#[derive(Clone)]
+pub struct RecordedFrame {
+    pub scene: Scene,
+    pub base_color: Color,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Shared log of the frames a [`RecordingRenderer`] received
+#[derive(Clone, Default)]
+pub struct SceneLog {
+    frames: Arc<Mutex<Vec<RecordedFrame>>>,
+}
+
+impl SceneLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of frames rendered so far
+    pub fn len(&self) -> usize {
+        self.frames.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The most recently rendered frame
+    pub fn last(&self) -> Option<RecordedFrame> {
+        self.frames.lock().unwrap().last().cloned()
+    }
+
+    /// Remove and return all recorded frames
+    pub fn take(&self) -> Vec<RecordedFrame> {
+        std::mem::take(&mut *self.frames.lock().unwrap())
+    }
+
+    /// A factory producing recording backends that write into this log
+    pub fn factory(&self) -> BackendFactory {
+        let log = self.clone();
+        Arc::new(move |_window, width, height, _options| {
+            Ok(Box::new(RecordingRenderer::new(log.clone(), width, height)))
+        })
+    }
+}
+
+/// Backend that records submitted scenes instead of drawing them
+///
+/// Lets tests assert on what masonry painted without needing a GPU.
+pub struct RecordingRenderer {
+    log: SceneLog,
+    width: u32,
+    height: u32,
+}
+
+impl RecordingRenderer {
+    pub fn new(log: SceneLog, width: u32, height: u32) -> Self {
+        Self { log, width, height }
+    }
+}
+
+impl RenderBackend for RecordingRenderer {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+    }
+
+    fn render(&mut self, scene: &Scene, base_color: Color) -> Result<(), RenderError> {
+        self.log.frames.lock().unwrap().push(RecordedFrame {
+            scene: scene.clone(),
+            base_color,
+            width: self.width,
+            height: self.height,
+        });
+        Ok(())
+    }
+
+    fn read_pixels(
+        &mut self,
+        _scene: &Scene,
+        _base_color: Color,
+    ) -> Result<PixelBuffer, RenderError> {
+        Err(RenderError::Unsupported(
+            "recording backend doesn't rasterize scenes",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use raw_window_handle::{XlibDisplayHandle, XlibWindowHandle};
+
+    /// Handles of a window that doesn't exist, for backends that never present
+    fn no_window() -> WindowTarget {
+        WindowTarget::new(
+            RawWindowHandle::Xlib(XlibWindowHandle::empty()),
+            RawDisplayHandle::Xlib(XlibDisplayHandle::empty()),
+        )
+    }
+
+    #[test]
+    fn factory_creates_recording_backends_without_a_window() {
+        let log = SceneLog::new();
+        let factory = log.factory();
+        let mut backend = factory(&no_window(), 800, 600, &RenderOptions::default()).unwrap();
+        assert!(log.is_empty());
+
+        backend.render(&Scene::new(), Color::BLACK).unwrap();
+        backend.resize(400, 300);
+        backend.render(&Scene::new(), Color::WHITE).unwrap();
+
+        assert_eq!(log.len(), 2);
+        let last = log.last().unwrap();
+        assert_eq!((last.width, last.height), (400, 300));
+        assert_eq!(last.base_color.to_rgba8(), Color::WHITE.to_rgba8());
+        assert_eq!(log.take().len(), 2);
+        assert!(log.is_empty());
+    }
+
+    #[test]
+    fn read_pixels_is_unsupported() {
+        let mut renderer = RecordingRenderer::new(SceneLog::new(), 1, 1);
+        assert!(matches!(
+            renderer.read_pixels(&Scene::new(), Color::BLACK),
+            Err(RenderError::Unsupported(_))
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+//! Time source for animations and event timestamps
+//!
+//! Everything time-dependent goes through a [`Clock`] so tests can step time
+//! manually instead of depending on the wall clock.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A monotonic time source
+pub trait Clock: Send + Sync {
+    /// Time elapsed since the clock's origin
+    fn now(&self) -> Duration;
+}
+
+/// Wall clock time, starting when the clock was created
+#[derive(Clone, Copy, Debug)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A clock that only moves when told to
+///
+/// Clones share the same time, so a test can keep one and hand another to
+/// [`MasonryOptions::clock`](crate::MasonryOptions::clock).
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock {
+    now: Arc<Mutex<Duration>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move time forward
+    pub fn advance(&self, dt: Duration) {
+        *self.now.lock().unwrap() += dt;
+    }
+
+    /// Jump to an absolute time (may go backwards, deltas saturate at zero)
+    pub fn set(&self, now: Duration) {
+        *self.now.lock().unwrap() = now;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+}
+
+/// Animation time since the `last` frame, clamped so long pauses don't make animations jump
+///
+/// The first frame after a reset (`last` is None) doesn't advance animations.
+pub(crate) fn frame_delta(last: Option<Duration>, now: Duration, max: Duration) -> Duration {
+    last.map_or(Duration::ZERO, |last| now.saturating_sub(last))
+        .min(max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn manual_clock_clones_share_time() {
+        let clock = ManualClock::new();
+        let shared: Arc<dyn Clock> = Arc::new(clock.clone());
+        assert_eq!(shared.now(), Duration::ZERO);
+
+        clock.advance(Duration::from_millis(16));
+        clock.advance(Duration::from_millis(17));
+        assert_eq!(shared.now(), Duration::from_millis(33));
+
+        clock.set(Duration::from_secs(2));
+        assert_eq!(shared.now(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn first_frame_has_no_delta() {
+        let clock = ManualClock::new();
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(frame_delta(None, clock.now(), MAX), Duration::ZERO);
+    }
+
+    #[test]
+    fn delta_follows_the_clock() {
+        let clock = ManualClock::new();
+        let last = clock.now();
+        clock.advance(Duration::from_millis(16));
+        assert_eq!(
+            frame_delta(Some(last), clock.now(), MAX),
+            Duration::from_millis(16)
+        );
+    }
+
+    #[test]
+    fn delta_is_clamped_after_a_pause() {
+        let clock = ManualClock::new();
+        let last = clock.now();
+        clock.advance(Duration::from_secs(3));
+        assert_eq!(frame_delta(Some(last), clock.now(), MAX), MAX);
+    }
+
+    #[test]
+    fn delta_saturates_when_time_goes_backwards() {
+        let clock = ManualClock::new();
+        clock.set(Duration::from_secs(1));
+        let last = clock.now();
+        clock.set(Duration::from_millis(500));
+        assert_eq!(frame_delta(Some(last), clock.now(), MAX), Duration::ZERO);
+    }
+}
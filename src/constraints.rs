@@ -0,0 +1,207 @@
+//! Resize constraints for the editor window
+//!
+//! Hosts ask plugins how their editor may be resized (CLAP's `can_resize`,
+//! `get_resize_hints` and `adjust_size`, VST3's `checkSizeConstraint`).
+//! [`ResizeConstraints`] answers those and is enforced when the window is
+//! resized anyway.
+
+use baseview::Size;
+
+/// Limits on the editor's logical size
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResizeConstraints {
+    /// Smallest allowed size
+    pub min: Option<Size>,
+    /// Largest allowed size
+    pub max: Option<Size>,
+    /// Fixed width / height ratio
+    pub aspect_ratio: Option<f64>,
+    /// Width and height only grow in these increments, counted from `min`
+    pub step: Option<(u32, u32)>,
+}
+
+impl Default for ResizeConstraints {
+    fn default() -> Self {
+        Self::UNCONSTRAINED
+    }
+}
+
+impl ResizeConstraints {
+    /// Any size goes
+    pub const UNCONSTRAINED: Self = Self {
+        min: None,
+        max: None,
+        aspect_ratio: None,
+        step: None,
+    };
+
+    /// Only `size` is allowed
+    pub fn fixed(size: Size) -> Self {
+        Self {
+            min: Some(size),
+            max: Some(size),
+            ..Self::UNCONSTRAINED
+        }
+    }
+
+    /// Whether any size other than the minimum is allowed
+    pub fn is_resizable(&self) -> bool {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => min != max,
+            _ => true,
+        }
+    }
+
+    /// Closest allowed size that fits into `size`
+    ///
+    /// Sizes are shrunk to match the aspect ratio and steps, except that the
+    /// minimum always wins.
+    pub fn adjust_size(&self, size: Size) -> Size {
+        let mut width = self.clamp_width(size.width);
+        let mut height = self.clamp_height(size.height);
+
+        if let Some(ratio) = self.aspect_ratio.filter(|ratio| *ratio > 0.0) {
+            // Fit the largest rectangle with the ratio into the proposal
+            width = width.min(height * ratio);
+            height = width / ratio;
+        }
+
+        if let Some((step_width, step_height)) = self.step {
+            let (min_width, min_height) =
+                self.min.map_or((0.0, 0.0), |min| (min.width, min.height));
+            width = snap(width, min_width, step_width);
+            height = snap(height, min_height, step_height);
+        }
+
+        Size::new(self.clamp_width(width), self.clamp_height(height))
+    }
+
+    fn clamp_width(&self, width: f64) -> f64 {
+        let width = self.max.map_or(width, |max| width.min(max.width));
+        self.min.map_or(width, |min| width.max(min.width))
+    }
+
+    fn clamp_height(&self, height: f64) -> f64 {
+        let height = self.max.map_or(height, |max| height.min(max.height));
+        self.min.map_or(height, |min| height.max(min.height))
+    }
+}
+
+/// Round `value` down to `origin` plus a multiple of `step`
+fn snap(value: f64, origin: f64, step: u32) -> f64 {
+    if step == 0 || value <= origin {
+        return value;
+    }
+    let step = step as f64;
+    origin + ((value - origin) / step).floor() * step
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constraints(min: (f64, f64), max: (f64, f64)) -> ResizeConstraints {
+        ResizeConstraints {
+            min: Some(Size::new(min.0, min.1)),
+            max: Some(Size::new(max.0, max.1)),
+            ..ResizeConstraints::UNCONSTRAINED
+        }
+    }
+
+    #[test]
+    fn unconstrained_keeps_size() {
+        let size = Size::new(123.0, 456.0);
+        assert_eq!(ResizeConstraints::UNCONSTRAINED.adjust_size(size), size);
+    }
+
+    #[test]
+    fn clamps_to_min_and_max() {
+        let c = constraints((200.0, 100.0), (800.0, 600.0));
+        assert_eq!(
+            c.adjust_size(Size::new(50.0, 50.0)),
+            Size::new(200.0, 100.0)
+        );
+        assert_eq!(
+            c.adjust_size(Size::new(1000.0, 1000.0)),
+            Size::new(800.0, 600.0)
+        );
+        assert_eq!(
+            c.adjust_size(Size::new(400.0, 300.0)),
+            Size::new(400.0, 300.0)
+        );
+    }
+
+    #[test]
+    fn fits_aspect_ratio_inside_proposal() {
+        let c = ResizeConstraints {
+            aspect_ratio: Some(2.0),
+            ..ResizeConstraints::UNCONSTRAINED
+        };
+        // Too wide: the height limits
+        assert_eq!(
+            c.adjust_size(Size::new(500.0, 100.0)),
+            Size::new(200.0, 100.0)
+        );
+        // Too tall: the width limits
+        assert_eq!(
+            c.adjust_size(Size::new(200.0, 300.0)),
+            Size::new(200.0, 100.0)
+        );
+    }
+
+    #[test]
+    fn min_wins_over_aspect_ratio() {
+        let c = ResizeConstraints {
+            min: Some(Size::new(100.0, 100.0)),
+            aspect_ratio: Some(4.0),
+            ..ResizeConstraints::UNCONSTRAINED
+        };
+        assert_eq!(
+            c.adjust_size(Size::new(200.0, 200.0)),
+            Size::new(200.0, 100.0)
+        );
+    }
+
+    #[test]
+    fn snaps_to_steps_from_min() {
+        let c = ResizeConstraints {
+            min: Some(Size::new(105.0, 50.0)),
+            step: Some((10, 25)),
+            ..ResizeConstraints::UNCONSTRAINED
+        };
+        assert_eq!(
+            c.adjust_size(Size::new(139.0, 124.0)),
+            Size::new(135.0, 100.0)
+        );
+        assert_eq!(
+            c.adjust_size(Size::new(105.0, 50.0)),
+            Size::new(105.0, 50.0)
+        );
+    }
+
+    #[test]
+    fn snap_ignores_zero_step_and_values_below_origin() {
+        assert_eq!(snap(37.0, 0.0, 0), 37.0);
+        assert_eq!(snap(5.0, 10.0, 4), 5.0);
+        assert_eq!(snap(17.0, 10.0, 4), 14.0);
+    }
+
+    #[test]
+    fn fixed_only_allows_its_size() {
+        let size = Size::new(640.0, 480.0);
+        let c = ResizeConstraints::fixed(size);
+        assert!(!c.is_resizable());
+        assert_eq!(c.adjust_size(Size::new(10.0, 2000.0)), size);
+    }
+
+    #[test]
+    fn resizable_unless_min_equals_max() {
+        assert!(ResizeConstraints::UNCONSTRAINED.is_resizable());
+        assert!(constraints((100.0, 100.0), (200.0, 200.0)).is_resizable());
+        let only_min = ResizeConstraints {
+            min: Some(Size::new(100.0, 100.0)),
+            ..ResizeConstraints::UNCONSTRAINED
+        };
+        assert!(only_min.is_resizable());
+    }
+}
@@ -0,0 +1,339 @@
+//! Damage tracking for partial repaints
+//!
+//! Masonry doesn't report which pixels changed, but every widget that calls
+//! `request_render` also gets its accessibility node rebuilt. We mirror the
+//! accesskit tree to turn the nodes in each `TreeUpdate` into window rectangles,
+//! and add the widgets under the pointer since hover and press feedback usually
+//! only requests a paint.
+
+use masonry::accesskit::{NodeId, TreeUpdate};
+use std::collections::HashMap;
+use vello::kurbo::{Affine, Point, Rect};
+
+/// Mirror of one accesskit node's geometry
+struct NodeGeometry {
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    transform: Affine,
+    bounds: Option<Rect>,
+}
+
+/// Accumulates the regions that need repainting before the next frame
+pub struct DamageTracker {
+    nodes: HashMap<NodeId, NodeGeometry>,
+    root: Option<NodeId>,
+    damage: Option<Rect>,
+    full: bool,
+}
+
+impl DamageTracker {
+    pub fn new() -> Self {
+        Self {
+            nodes: HashMap::new(),
+            root: None,
+            damage: None,
+            full: true,
+        }
+    }
+
+    /// Repaint the whole window next frame (resize, rescale, first frame...)
+    pub fn damage_all(&mut self) {
+        self.full = true;
+    }
+
+    /// Mark a window rectangle (physical pixels) as needing a repaint
+    pub fn add(&mut self, rect: Rect) {
+        if rect.area() <= 0.0 {
+            return;
+        }
+        self.damage = Some(match self.damage {
+            Some(damage) => damage.union(rect),
+            None => rect,
+        });
+    }
+
+    /// Damage the innermost widget containing a window position
+    pub fn add_at(&mut self, position: Point) {
+        if let Some(rect) = self.hit_test(position) {
+            self.add(rect);
+        }
+    }
+
+    /// Apply a masonry accessibility update, damaging old and new bounds of every changed node
+    pub fn apply(&mut self, update: &TreeUpdate) {
+        if let Some(tree) = &update.tree {
+            if self.root != Some(tree.root) {
+                self.root = Some(tree.root);
+                self.full = true;
+            }
+        }
+
+        // Where changed nodes used to be
+        for (id, _) in &update.nodes {
+            if let Some(rect) = self.window_rect(*id) {
+                self.add(rect);
+            }
+        }
+
+        for (id, node) in &update.nodes {
+            let transform = node
+                .transform()
+                .map(|t| Affine::new(t.as_coeffs()))
+                .unwrap_or(Affine::IDENTITY);
+            let bounds = node.bounds().map(|r| Rect::new(r.x0, r.y0, r.x1, r.y1));
+            let children = node.children().to_vec();
+            let parent = self.nodes.get(id).and_then(|n| n.parent);
+
+            let removed: Vec<NodeId> = self
+                .nodes
+                .get(id)
+                .map(|old| {
+                    old.children
+                        .iter()
+                        .filter(|child| !children.contains(child))
+                        .copied()
+                        .collect()
+                })
+                .unwrap_or_default();
+            for child in removed {
+                self.remove_subtree(child);
+            }
+
+            self.nodes.insert(
+                *id,
+                NodeGeometry {
+                    parent,
+                    children,
+                    transform,
+                    bounds,
+                },
+            );
+        }
+
+        // Link children to their parents once all nodes are known
+        for (id, node) in &update.nodes {
+            for child in node.children() {
+                if let Some(child) = self.nodes.get_mut(child) {
+                    child.parent = Some(*id);
+                }
+            }
+        }
+
+        // Where changed nodes are now
+        for (id, _) in &update.nodes {
+            if let Some(rect) = self.window_rect(*id) {
+                self.add(rect);
+            }
+        }
+    }
+
+    /// Take the accumulated damage for this frame
+    ///
+    /// Returns `Damage::Full` if the whole window must be repainted.
+    pub fn take(&mut self) -> Damage {
+        let damage = self.damage.take();
+        if std::mem::take(&mut self.full) {
+            return Damage::Full;
+        }
+        match damage {
+            Some(rect) => Damage::Partial(rect),
+            None => Damage::None,
+        }
+    }
+
+    fn remove_subtree(&mut self, id: NodeId) {
+        if let Some(node) = self.nodes.remove(&id) {
+            for child in node.children {
+                self.remove_subtree(child);
+            }
+        }
+    }
+
+    /// Transform from a node's local coordinates to window coordinates
+    fn window_transform(&self, id: NodeId) -> Option<Affine> {
+        let mut transform = Affine::IDENTITY;
+        let mut current = Some(id);
+        while let Some(id) = current {
+            let node = self.nodes.get(&id)?;
+            transform = node.transform * transform;
+            current = node.parent;
+        }
+        Some(transform)
+    }
+
+    fn window_rect(&self, id: NodeId) -> Option<Rect> {
+        let bounds = self.nodes.get(&id)?.bounds?;
+        Some(self.window_transform(id)?.transform_rect_bbox(bounds))
+    }
+
+    fn hit_test(&self, position: Point) -> Option<Rect> {
+        let mut current = self.root?;
+        let mut hit = self.window_rect(current);
+        'descend: loop {
+            let node = self.nodes.get(&current)?;
+            // Later children are painted on top
+            for child in node.children.iter().rev() {
+                if let Some(rect) = self.window_rect(*child) {
+                    if rect.contains(position) {
+                        current = *child;
+                        hit = Some(rect);
+                        continue 'descend;
+                    }
+                }
+            }
+            return hit;
+        }
+    }
+}
+
+/// What needs repainting this frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Damage {
+    /// Nothing changed
+    None,
+    /// Only this window rectangle changed (physical pixels)
+    Partial(Rect),
+    /// Everything must be repainted
+    Full,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use masonry::accesskit::{self, Node, Role, Tree};
+
+    fn node(bounds: (f64, f64, f64, f64), transform: Option<[f64; 6]>, children: &[u64]) -> Node {
+        let mut node = Node::new(Role::GenericContainer);
+        node.set_bounds(accesskit::Rect::new(bounds.0, bounds.1, bounds.2, bounds.3));
+        if let Some(coeffs) = transform {
+            node.set_transform(accesskit::Affine::new(coeffs));
+        }
+        node.set_children(children.iter().copied().map(NodeId).collect::<Vec<_>>());
+        node
+    }
+
+    fn update(root: Option<u64>, nodes: Vec<(u64, Node)>) -> TreeUpdate {
+        TreeUpdate {
+            nodes: nodes
+                .into_iter()
+                .map(|(id, node)| (NodeId(id), node))
+                .collect(),
+            tree: root.map(|root| Tree::new(NodeId(root))),
+            focus: NodeId(0),
+        }
+    }
+
+    /// Root filling a 100x100 window, a child translated by (10, 20) and a
+    /// grandchild scaled by 2 and offset by (5, 5)
+    fn nested() -> DamageTracker {
+        let mut tracker = DamageTracker::new();
+        tracker.apply(&update(
+            Some(0),
+            vec![
+                (0, node((0.0, 0.0, 100.0, 100.0), None, &[1])),
+                (
+                    1,
+                    node(
+                        (0.0, 0.0, 50.0, 50.0),
+                        Some([1.0, 0.0, 0.0, 1.0, 10.0, 20.0]),
+                        &[2],
+                    ),
+                ),
+                (
+                    2,
+                    node(
+                        (0.0, 0.0, 10.0, 10.0),
+                        Some([2.0, 0.0, 0.0, 2.0, 5.0, 5.0]),
+                        &[],
+                    ),
+                ),
+            ],
+        ));
+        assert_eq!(tracker.take(), Damage::Full);
+        tracker
+    }
+
+    #[test]
+    fn nested_transforms_compose() {
+        let tracker = nested();
+        assert_eq!(
+            tracker.window_transform(NodeId(2)),
+            Some(Affine::new([2.0, 0.0, 0.0, 2.0, 15.0, 25.0]))
+        );
+        assert_eq!(
+            tracker.window_rect(NodeId(2)),
+            Some(Rect::new(15.0, 25.0, 35.0, 45.0))
+        );
+    }
+
+    #[test]
+    fn moved_nodes_damage_old_and_new_bounds() {
+        let mut tracker = nested();
+        tracker.apply(&update(
+            None,
+            vec![(
+                2,
+                node(
+                    (20.0, 0.0, 30.0, 10.0),
+                    Some([2.0, 0.0, 0.0, 2.0, 5.0, 5.0]),
+                    &[],
+                ),
+            )],
+        ));
+        assert_eq!(
+            tracker.take(),
+            Damage::Partial(Rect::new(15.0, 25.0, 75.0, 45.0))
+        );
+        assert_eq!(tracker.take(), Damage::None);
+    }
+
+    #[test]
+    fn removed_children_drop_their_subtree() {
+        let mut tracker = nested();
+        tracker.apply(&update(
+            None,
+            vec![(0, node((0.0, 0.0, 100.0, 100.0), None, &[]))],
+        ));
+        assert_eq!(tracker.nodes.len(), 1);
+        assert_eq!(tracker.window_rect(NodeId(2)), None);
+        assert_eq!(
+            tracker.take(),
+            Damage::Partial(Rect::new(0.0, 0.0, 100.0, 100.0))
+        );
+    }
+
+    #[test]
+    fn new_root_repaints_everything() {
+        let mut tracker = nested();
+        tracker.apply(&update(Some(0), vec![]));
+        assert_eq!(tracker.take(), Damage::None);
+        tracker.apply(&update(
+            Some(3),
+            vec![(3, node((0.0, 0.0, 100.0, 100.0), None, &[]))],
+        ));
+        assert_eq!(tracker.take(), Damage::Full);
+    }
+
+    #[test]
+    fn pointer_damages_the_innermost_widget() {
+        let mut tracker = nested();
+        assert_eq!(
+            tracker.hit_test(Point::new(20.0, 30.0)),
+            Some(Rect::new(15.0, 25.0, 35.0, 45.0))
+        );
+        assert_eq!(
+            tracker.hit_test(Point::new(50.0, 50.0)),
+            Some(Rect::new(10.0, 20.0, 60.0, 70.0))
+        );
+        assert_eq!(
+            tracker.hit_test(Point::new(90.0, 90.0)),
+            Some(Rect::new(0.0, 0.0, 100.0, 100.0))
+        );
+
+        tracker.add_at(Point::new(20.0, 30.0));
+        assert_eq!(
+            tracker.take(),
+            Damage::Partial(Rect::new(15.0, 25.0, 35.0, 45.0))
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Fixed design-size scaling
+//!
+//! Editors designed pixel-perfect at one size can keep their layout and scale
+//! uniformly with the window instead. Masonry always lays out at the design
+//! size; the finished scene is scaled to the window and pointer positions are
+//! mapped back into design coordinates.
+
+use crate::damage::Damage;
+use baseview::Size;
+use vello::kurbo::{Affine, Vec2};
+use vello::Scene;
+
+/// How the design size is fitted into the window
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DesignFit {
+    /// Scale uniformly and center, filling the rest with the base color (default)
+    #[default]
+    Letterbox,
+    /// Scale each axis on its own to fill the whole window
+    Stretch,
+}
+
+/// Lay the editor out at a fixed logical size and scale it to the window
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DesignSize {
+    /// The size the editor was designed at
+    pub size: Size,
+    pub fit: DesignFit,
+}
+
+impl DesignSize {
+    pub fn new(width: f64, height: f64) -> Self {
+        Self {
+            size: Size::new(width, height),
+            fit: DesignFit::default(),
+        }
+    }
+
+    pub fn with_fit(mut self, fit: DesignFit) -> Self {
+        self.fit = fit;
+        self
+    }
+
+    /// Transform from design coordinates to a window of the given physical size
+    pub(crate) fn transform(&self, width: f64, height: f64) -> Affine {
+        let design_width = self.size.width.max(1.0);
+        let design_height = self.size.height.max(1.0);
+        let scale_x = width / design_width;
+        let scale_y = height / design_height;
+
+        match self.fit {
+            DesignFit::Letterbox => {
+                let scale = scale_x.min(scale_y);
+                let offset = Vec2::new(
+                    (width - design_width * scale) / 2.0,
+                    (height - design_height * scale) / 2.0,
+                );
+                Affine::translate(offset) * Affine::scale(scale)
+            }
+            DesignFit::Stretch => Affine::scale_non_uniform(scale_x, scale_y),
+        }
+    }
+}
+
+/// Scale a frame laid out at the design size to the window
+pub(crate) fn scale_frame(scene: Scene, damage: Damage, transform: Affine) -> (Scene, Damage) {
+    let mut scaled = Scene::new();
+    scaled.append(&scene, Some(transform));
+    let damage = match damage {
+        // Antialiasing bleeds into the neighboring pixels
+        Damage::Partial(rect) => {
+            Damage::Partial(transform.transform_rect_bbox(rect).inflate(1.0, 1.0))
+        }
+        damage => damage,
+    };
+    (scaled, damage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vello::kurbo::{Point, Rect};
+    use vello::peniko::{Color, Fill};
+
+    #[test]
+    fn letterbox_centers_the_scaled_design() {
+        // A 400x300 design in an 800x800 window is doubled and letterboxed vertically
+        let transform = DesignSize::new(400.0, 300.0).transform(800.0, 800.0);
+        assert_eq!(transform * Point::ZERO, Point::new(0.0, 100.0));
+        assert_eq!(
+            transform * Point::new(400.0, 300.0),
+            Point::new(800.0, 700.0)
+        );
+    }
+
+    #[test]
+    fn stretch_fills_the_window() {
+        let transform = DesignSize::new(400.0, 300.0)
+            .with_fit(DesignFit::Stretch)
+            .transform(800.0, 900.0);
+        assert_eq!(transform * Point::ZERO, Point::ZERO);
+        assert_eq!(
+            transform * Point::new(400.0, 300.0),
+            Point::new(800.0, 900.0)
+        );
+    }
+
+    #[test]
+    fn scaled_damage_covers_antialiasing() {
+        let transform = DesignSize::new(400.0, 300.0).transform(800.0, 800.0);
+        let widget = Rect::new(10.0, 10.0, 20.0, 20.0);
+        let mut scene = Scene::new();
+        scene.fill(Fill::NonZero, Affine::IDENTITY, Color::WHITE, None, &widget);
+
+        let (scaled, damage) = scale_frame(scene, Damage::Partial(widget), transform);
+        assert_eq!(damage, Damage::Partial(Rect::new(19.0, 119.0, 41.0, 141.0)));
+        let applied = scaled.encoding().transforms.last().unwrap();
+        assert_eq!(applied.matrix, [2.0, 0.0, 0.0, 2.0]);
+        assert_eq!(applied.translation, [0.0, 100.0]);
+    }
+
+    #[test]
+    fn full_and_missing_damage_are_kept() {
+        let transform = Affine::scale(2.0);
+        let (_, damage) = scale_frame(Scene::new(), Damage::Full, transform);
+        assert_eq!(damage, Damage::Full);
+        let (_, damage) = scale_frame(Scene::new(), Damage::None, transform);
+        assert_eq!(damage, Damage::None);
+    }
+}
@@ -0,0 +1,404 @@
+//! Editor state that survives closing and reopening the editor
+//!
+//! Hosts destroy the editor window whenever the user closes it, and the
+//! widget builder starts from scratch on the next open. [`EditorState`] keeps
+//! the bits worth restoring (scroll positions, selected tabs, expanded panels)
+//! on the plugin's side of that boundary. Widgets write to it as the user
+//! interacts; the builder reads it back. It serializes to bytes so the plugin
+//! can store it in its project state, too.
+//!
+//! Masonry's `WidgetId`s are assigned at runtime and differ on every open, so
+//! entries are keyed by names the plugin chooses, like `"mixer.scroll"`. Keys
+//! starting with `masonry_baseview.` are used by the window itself.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Start of serialized editor state
+const MAGIC: &[u8; 4] = b"MBES";
+/// Version of the serialized format
+const VERSION: u8 = 1;
+
+const TAG_BOOL: u8 = 0;
+const TAG_INT: u8 = 1;
+const TAG_FLOAT: u8 = 2;
+const TAG_TEXT: u8 = 3;
+
+/// A value in the [`EditorState`] store
+#[derive(Clone, Debug, PartialEq)]
+pub enum StateValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl From<bool> for StateValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i64> for StateValue {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<f64> for StateValue {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<String> for StateValue {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+
+impl From<&str> for StateValue {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_string())
+    }
+}
+
+/// Errors reading serialized editor state
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StateError {
+    /// The data doesn't start like serialized editor state
+    NotEditorState,
+    /// Written by a newer version of this crate
+    UnsupportedVersion(u8),
+    /// The data ends in the middle of an entry
+    Truncated,
+    /// A key or text value isn't valid UTF-8
+    InvalidText,
+    /// An entry has a type this version doesn't know
+    UnknownType(u8),
+}
+
+impl std::fmt::Display for StateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotEditorState => write!(f, "Not editor state"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "Unsupported editor state version: {}", version)
+            }
+            Self::Truncated => write!(f, "Editor state is truncated"),
+            Self::InvalidText => write!(f, "Editor state contains invalid UTF-8"),
+            Self::UnknownType(tag) => write!(f, "Unknown editor state value type: {}", tag),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+/// Key-value store for editor state, kept across open/close cycles
+///
+/// Keep one per plugin instance and share clones between the widget builder
+/// and [`MasonryOptions::editor_state`](crate::MasonryOptions::editor_state).
+/// Save [`to_bytes`](Self::to_bytes) with the plugin's state and
+/// [`restore`](Self::restore) it when the project is loaded.
+#[derive(Clone, Default)]
+pub struct EditorState {
+    entries: Arc<Mutex<BTreeMap<String, StateValue>>>,
+}
+
+impl EditorState {
+    /// Key the window stores its user zoom factor under
+    pub const ZOOM_KEY: &'static str = "masonry_baseview.zoom";
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<StateValue> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.get(key)? {
+            StateValue::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_int(&self, key: &str) -> Option<i64> {
+        match self.get(key)? {
+            StateValue::Int(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Float value, integers are converted
+    pub fn get_float(&self, key: &str) -> Option<f64> {
+        match self.get(key)? {
+            StateValue::Float(value) => Some(value),
+            StateValue::Int(value) => Some(value as f64),
+            _ => None,
+        }
+    }
+
+    pub fn get_text(&self, key: &str) -> Option<String> {
+        match self.get(key)? {
+            StateValue::Text(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn set(&self, key: impl Into<String>, value: impl Into<StateValue>) {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.into(), value.into());
+    }
+
+    pub fn remove(&self, key: &str) -> Option<StateValue> {
+        self.entries.lock().unwrap().remove(key)
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().unwrap().is_empty()
+    }
+
+    /// Serialize all entries, e.g. for CLAP's `state.save`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let entries = self.entries.lock().unwrap();
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        for (key, value) in entries.iter() {
+            write_text(&mut bytes, key);
+            match value {
+                StateValue::Bool(value) => {
+                    bytes.push(TAG_BOOL);
+                    bytes.push(*value as u8);
+                }
+                StateValue::Int(value) => {
+                    bytes.push(TAG_INT);
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+                StateValue::Float(value) => {
+                    bytes.push(TAG_FLOAT);
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+                StateValue::Text(value) => {
+                    bytes.push(TAG_TEXT);
+                    write_text(&mut bytes, value);
+                }
+            }
+        }
+        bytes
+    }
+
+    /// Read state written by [`to_bytes`](Self::to_bytes)
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StateError> {
+        let state = Self::new();
+        state.restore(bytes)?;
+        Ok(state)
+    }
+
+    /// Replace all entries with serialized state, e.g. from CLAP's `state.load`
+    ///
+    /// Clones see the new entries. On error the entries are left unchanged.
+    pub fn restore(&self, bytes: &[u8]) -> Result<(), StateError> {
+        let mut reader = Reader { bytes };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(StateError::NotEditorState);
+        }
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+
+        let count = reader.u32()?;
+        let mut entries = BTreeMap::new();
+        for _ in 0..count {
+            let key = reader.text()?;
+            let value = match reader.u8()? {
+                TAG_BOOL => StateValue::Bool(reader.u8()? != 0),
+                TAG_INT => StateValue::Int(i64::from_le_bytes(reader.array()?)),
+                TAG_FLOAT => StateValue::Float(f64::from_le_bytes(reader.array()?)),
+                TAG_TEXT => StateValue::Text(reader.text()?),
+                tag => return Err(StateError::UnknownType(tag)),
+            };
+            entries.insert(key, value);
+        }
+
+        *self.entries.lock().unwrap() = entries;
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for EditorState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.entries.lock().unwrap().iter())
+            .finish()
+    }
+}
+
+/// Length-prefixed UTF-8
+fn write_text(bytes: &mut Vec<u8>, text: &str) {
+    bytes.extend_from_slice(&(text.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(text.as_bytes());
+}
+
+/// Cursor over serialized state
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if self.bytes.len() < len {
+            return Err(StateError::Truncated);
+        }
+        let (head, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, StateError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn text(&mut self) -> Result<String, StateError> {
+        let len = self.u32()? as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| StateError::InvalidText)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> EditorState {
+        let state = EditorState::new();
+        state.set("panel.expanded", true);
+        state.set("tabs.selected", 3i64);
+        state.set("mixer.scroll", 120.5);
+        state.set("preset.filter", "bass");
+        state
+    }
+
+    /// Header for `count` entries
+    fn header(count: u32) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&count.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let state = sample();
+        let restored = EditorState::from_bytes(&state.to_bytes()).unwrap();
+        assert_eq!(restored.get_bool("panel.expanded"), Some(true));
+        assert_eq!(restored.get_int("tabs.selected"), Some(3));
+        assert_eq!(restored.get_float("mixer.scroll"), Some(120.5));
+        assert_eq!(restored.get_text("preset.filter").as_deref(), Some("bass"));
+        assert_eq!(restored.to_bytes(), state.to_bytes());
+    }
+
+    #[test]
+    fn empty_round_trip() {
+        let restored = EditorState::from_bytes(&EditorState::new().to_bytes()).unwrap();
+        assert!(restored.is_empty());
+    }
+
+    #[test]
+    fn restore_replaces_entries_for_all_clones() {
+        let state = EditorState::new();
+        state.set("stale", 1i64);
+        let clone = state.clone();
+        state.restore(&sample().to_bytes()).unwrap();
+        assert_eq!(clone.get("stale"), None);
+        assert_eq!(clone.get_int("tabs.selected"), Some(3));
+    }
+
+    #[test]
+    fn truncated_input() {
+        let bytes = sample().to_bytes();
+        for len in 0..bytes.len() {
+            let error = EditorState::from_bytes(&bytes[..len]).unwrap_err();
+            assert_eq!(error, StateError::Truncated, "length {}", len);
+        }
+    }
+
+    #[test]
+    fn failed_restore_keeps_entries() {
+        let state = sample();
+        let bytes = state.to_bytes();
+        assert!(state.restore(&bytes[..bytes.len() - 1]).is_err());
+        assert_eq!(state.get_text("preset.filter").as_deref(), Some("bass"));
+    }
+
+    #[test]
+    fn bad_magic() {
+        let mut bytes = sample().to_bytes();
+        bytes[0] = b'X';
+        assert_eq!(
+            EditorState::from_bytes(&bytes).unwrap_err(),
+            StateError::NotEditorState
+        );
+    }
+
+    #[test]
+    fn wrong_version() {
+        let mut bytes = sample().to_bytes();
+        bytes[MAGIC.len()] = VERSION + 1;
+        assert_eq!(
+            EditorState::from_bytes(&bytes).unwrap_err(),
+            StateError::UnsupportedVersion(VERSION + 1)
+        );
+    }
+
+    #[test]
+    fn unknown_tag() {
+        let mut bytes = header(1);
+        write_text(&mut bytes, "key");
+        bytes.push(42);
+        assert_eq!(
+            EditorState::from_bytes(&bytes).unwrap_err(),
+            StateError::UnknownType(42)
+        );
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let mut bytes = header(1);
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        bytes.extend_from_slice(&[0xff, 0xfe]);
+        bytes.push(TAG_BOOL);
+        bytes.push(1);
+        assert_eq!(
+            EditorState::from_bytes(&bytes).unwrap_err(),
+            StateError::InvalidText
+        );
+    }
+
+    #[test]
+    fn typed_getters_check_the_type() {
+        let state = sample();
+        assert_eq!(state.get_int("panel.expanded"), None);
+        assert_eq!(state.get_text("mixer.scroll"), None);
+        // Integers read as floats
+        assert_eq!(state.get_float("tabs.selected"), Some(3.0));
+    }
+}
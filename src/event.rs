@@ -2,14 +2,18 @@
 //!
 //! Converts baseview events into masonry-compatible pointer and keyboard events.
 
+use crate::clock::Clock;
 use baseview::{Event, MouseButton, MouseEvent, ScrollDelta, WindowEvent};
 use keyboard_types::Modifiers as KbModifiers;
+use masonry::core::keyboard;
 use masonry::core::pointer::PointerButtons;
 use masonry::core::{
     Modifiers, PointerButton, PointerButtonEvent, PointerEvent, PointerInfo, PointerId,
     PointerScrollEvent, PointerState, PointerType, PointerUpdate,
 };
 use masonry::dpi::PhysicalPosition;
+use std::sync::Arc;
+use vello::kurbo::{Affine, Point};
 
 /// Translate a baseview mouse button to masonry pointer button
 pub fn translate_mouse_button(button: MouseButton) -> PointerButton {
@@ -41,6 +45,38 @@ pub fn translate_modifiers(mods: KbModifiers) -> Modifiers {
     result
 }
 
+/// Translate a baseview keyboard event to masonry's
+///
+/// Baseview and masonry use different keyboard-types versions, so keys and
+/// codes are matched by name.
+pub fn translate_keyboard_event(event: &keyboard_types::KeyboardEvent) -> keyboard::KeyboardEvent {
+    keyboard::KeyboardEvent {
+        state: match event.state {
+            keyboard_types::KeyState::Down => keyboard::KeyState::Down,
+            keyboard_types::KeyState::Up => keyboard::KeyState::Up,
+        },
+        key: event
+            .key
+            .to_string()
+            .parse()
+            .unwrap_or(keyboard::Key::Named(keyboard::NamedKey::Unidentified)),
+        code: event
+            .code
+            .to_string()
+            .parse()
+            .unwrap_or(keyboard::Code::Unidentified),
+        location: match event.location {
+            keyboard_types::Location::Standard => keyboard::Location::Standard,
+            keyboard_types::Location::Left => keyboard::Location::Left,
+            keyboard_types::Location::Right => keyboard::Location::Right,
+            keyboard_types::Location::Numpad => keyboard::Location::Numpad,
+        },
+        modifiers: translate_modifiers(event.modifiers),
+        repeat: event.repeat,
+        is_composing: event.is_composing,
+    }
+}
+
 /// Event translator that maintains pointer state between events
 pub struct EventTranslator {
     pointer_x: f64,
@@ -48,18 +84,24 @@ pub struct EventTranslator {
     buttons: PointerButtons,
     modifiers: Modifiers,
     scale_factor: f64,
-    start_time: std::time::Instant,
+    /// User zoom on top of the window scale, part of masonry's scale factor
+    zoom: f64,
+    /// Maps window physical positions into design coordinates, if scaling to a design size
+    design_transform: Option<Affine>,
+    clock: Arc<dyn Clock>,
 }
 
 impl EventTranslator {
-    pub fn new(scale_factor: f64) -> Self {
+    pub fn new(scale_factor: f64, clock: Arc<dyn Clock>) -> Self {
         Self {
             pointer_x: 0.0,
             pointer_y: 0.0,
             buttons: PointerButtons::default(),
             modifiers: Modifiers::empty(),
             scale_factor,
-            start_time: std::time::Instant::now(),
+            zoom: 1.0,
+            design_transform: None,
+            clock,
         }
     }
 
@@ -67,6 +109,18 @@ impl EventTranslator {
         self.scale_factor = scale;
     }
 
+    /// Zoom masonry applies on top of the window scale
+    pub fn set_zoom(&mut self, zoom: f64) {
+        self.zoom = zoom;
+    }
+
+    /// Map pointer positions from window physical pixels into a design size
+    ///
+    /// `transform` is the inverse of the one scaling the scene to the window.
+    pub fn set_design_transform(&mut self, transform: Option<Affine>) {
+        self.design_transform = transform;
+    }
+
     /// Translate a baseview event into masonry events
     /// Returns None if the event doesn't map to a masonry event
     pub fn translate(&mut self, event: &Event) -> Option<MasonryEvent> {
@@ -78,7 +132,7 @@ impl EventTranslator {
     }
 
     fn get_time_nanos(&self) -> u64 {
-        self.start_time.elapsed().as_nanos() as u64
+        self.clock.now().as_nanos() as u64
     }
 
     fn make_pointer_info(&self) -> PointerInfo {
@@ -90,12 +144,20 @@ impl EventTranslator {
     }
 
     fn make_pointer_state(&self) -> PointerState {
+        let mut position = Point::new(
+            self.pointer_x * self.scale_factor,
+            self.pointer_y * self.scale_factor,
+        );
+        // Masonry lays out a design size at scale 1.0
+        let mut scale_factor = self.scale_factor * self.zoom;
+        if let Some(transform) = self.design_transform {
+            position = transform * position;
+            scale_factor = 1.0;
+        }
+
         PointerState {
             time: self.get_time_nanos(),
-            position: PhysicalPosition::new(
-                self.pointer_x * self.scale_factor,
-                self.pointer_y * self.scale_factor,
-            ),
+            position: PhysicalPosition::new(position.x, position.y),
             buttons: self.buttons.clone(),
             modifiers: self.modifiers,
             count: 1,
@@ -103,7 +165,7 @@ impl EventTranslator {
             orientation: Default::default(),
             pressure: 0.0,
             tangential_pressure: 0.0,
-            scale_factor: self.scale_factor,
+            scale_factor,
         }
     }
 
@@ -156,13 +218,15 @@ impl EventTranslator {
                 self.modifiers = translate_modifiers(*modifiers);
 
                 let scroll_delta = match delta {
-                    ScrollDelta::Lines { x, y } => {
-                        masonry::core::ScrollDelta::LineDelta(*x, *y)
-                    }
+                    ScrollDelta::Lines { x, y } => masonry::core::ScrollDelta::LineDelta(*x, *y),
                     ScrollDelta::Pixels { x, y } => {
+                        let mut delta = Point::new(*x as f64, *y as f64);
+                        if let Some(transform) = self.design_transform {
+                            // Only the scale applies to a distance
+                            delta = Point::ZERO + (transform * delta - transform * Point::ZERO);
+                        }
                         masonry::core::ScrollDelta::PixelDelta(PhysicalPosition::new(
-                            *x as f64,
-                            *y as f64,
+                            delta.x, delta.y,
                         ))
                     }
                 };
@@ -193,8 +257,7 @@ impl EventTranslator {
         event: &keyboard_types::KeyboardEvent,
     ) -> Option<MasonryEvent> {
         self.modifiers = translate_modifiers(event.modifiers);
-        // For now, we pass through keyboard events
-        // A full implementation would need to translate keyboard_types to masonry's TextEvent
+        // Kept as baseview's event for the zoom shortcuts, converted when forwarded
         Some(MasonryEvent::Keyboard(event.clone()))
     }
 
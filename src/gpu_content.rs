@@ -0,0 +1,208 @@
+//! Custom GPU content embedded in Vello scenes
+//!
+//! Some widgets need drawing Vello can't express (3D views, shader-based
+//! spectrograms). They register a [`CustomRenderer`] and get a [`GpuContent`]
+//! back. Each frame `RenderContext` runs the renderer into a texture it owns,
+//! sharing its device and queue, and substitutes that texture for the
+//! placeholder image the widget drew at its layout rect.
+
+use std::sync::{Arc, Mutex, Weak};
+use vello::kurbo::{Affine, Rect};
+use vello::peniko::{Blob, ImageAlphaType, ImageBrush, ImageData, ImageFormat};
+use vello::wgpu::{CommandEncoder, Device, Queue, TextureFormat, TextureView};
+use vello::Scene;
+
+/// Draws custom wgpu content into a texture
+pub trait CustomRenderer: Send {
+    /// Record the passes drawing this content into `ctx.target`
+    ///
+    /// Called once per frame before Vello renders the scene. The target is
+    /// cleared to transparent and uses straight alpha.
+    fn render(&mut self, ctx: &mut CustomRenderContext<'_>);
+}
+
+/// Everything a [`CustomRenderer`] needs to draw a frame
+pub struct CustomRenderContext<'a> {
+    pub device: &'a Device,
+    pub queue: &'a Queue,
+    pub encoder: &'a mut CommandEncoder,
+    /// Texture to draw into
+    pub target: &'a TextureView,
+    pub format: TextureFormat,
+    /// Size of the target in physical pixels
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Renders a scene may still be waiting for: one in progress, one queued
+const FRAMES_IN_FLIGHT: u32 = 2;
+
+/// State shared between a [`GpuContent`] and the render context
+pub(crate) struct ContentSlot {
+    pub(crate) image: ImageData,
+    /// Replaced images that scenes on the render thread may still draw, with
+    /// the number of renders they are kept for
+    retired: Vec<(ImageData, u32)>,
+    pub(crate) renderer: Box<dyn CustomRenderer>,
+}
+
+impl ContentSlot {
+    /// Images the next rendered scene may draw, counting down retired ones
+    pub(crate) fn images_in_use(&mut self) -> Vec<ImageData> {
+        let mut images: Vec<ImageData> = self
+            .retired
+            .iter()
+            .map(|(image, _)| image.clone())
+            .collect();
+        images.push(self.image.clone());
+        for (_, renders_left) in &mut self.retired {
+            *renders_left -= 1;
+        }
+        self.retired.retain(|(_, renders_left)| *renders_left > 0);
+        images
+    }
+}
+
+/// Custom GPU content a widget can paint into its scene
+///
+/// Dropping every clone unregisters the content and frees its texture.
+#[derive(Clone)]
+pub struct GpuContent {
+    slot: Arc<Mutex<ContentSlot>>,
+}
+
+impl GpuContent {
+    /// Paint the content into `rect` (logical coordinates)
+    ///
+    /// `scale` is the window's scale factor, used to size the backing texture
+    /// in physical pixels.
+    pub fn paint(&self, scene: &mut Scene, rect: Rect, scale: f64) {
+        let width = (rect.width() * scale).round().max(1.0) as u32;
+        let height = (rect.height() * scale).round().max(1.0) as u32;
+
+        let image = {
+            let mut slot = self.slot.lock().unwrap();
+            if slot.image.width != width || slot.image.height != height {
+                // New size means a new texture, so use a new placeholder. Scenes
+                // already handed to the render thread still draw the old one.
+                let old = std::mem::replace(&mut slot.image, placeholder(width, height));
+                slot.retired.push((old, FRAMES_IN_FLIGHT));
+            }
+            slot.image.clone()
+        };
+
+        let transform = Affine::translate(rect.origin().to_vec2())
+            * Affine::scale_non_uniform(rect.width() / width as f64, rect.height() / height as f64);
+        scene.draw_image(&ImageBrush::new(image), transform);
+    }
+}
+
+/// Registry of the custom GPU content in a window
+///
+/// Share clones of it with the widget builder via [`RenderOptions`](crate::RenderOptions).
+#[derive(Clone, Default)]
+pub struct GpuContentRegistry {
+    slots: Arc<Mutex<Vec<Weak<Mutex<ContentSlot>>>>>,
+}
+
+impl GpuContentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a custom renderer, returning the content widgets paint with
+    pub fn register(&self, renderer: impl CustomRenderer + 'static) -> GpuContent {
+        let slot = Arc::new(Mutex::new(ContentSlot {
+            image: placeholder(1, 1),
+            retired: Vec::new(),
+            renderer: Box::new(renderer),
+        }));
+        self.slots.lock().unwrap().push(Arc::downgrade(&slot));
+        GpuContent { slot }
+    }
+
+    /// Whether any registered content is still alive
+    pub(crate) fn has_live(&self) -> bool {
+        !self.live().is_empty()
+    }
+
+    /// All live content, dropping registrations whose content is gone
+    pub(crate) fn live(&self) -> Vec<Arc<Mutex<ContentSlot>>> {
+        let mut slots = self.slots.lock().unwrap();
+        slots.retain(|slot| slot.strong_count() > 0);
+        slots.iter().filter_map(Weak::upgrade).collect()
+    }
+}
+
+impl std::fmt::Debug for GpuContentRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GpuContentRegistry")
+            .field("slots", &self.slots.lock().unwrap().len())
+            .finish()
+    }
+}
+
+/// Image standing in for the texture; Vello swaps in the real one by blob id
+fn placeholder(width: u32, height: u32) -> ImageData {
+    ImageData {
+        data: Blob::new(Arc::new(Vec::<u8>::new())),
+        format: ImageFormat::Rgba8,
+        alpha_type: ImageAlphaType::Alpha,
+        width,
+        height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoContent;
+
+    impl CustomRenderer for NoContent {
+        fn render(&mut self, _ctx: &mut CustomRenderContext<'_>) {}
+    }
+
+    #[test]
+    fn replaced_images_stay_in_use_for_frames_in_flight() {
+        let registry = GpuContentRegistry::new();
+        let content = registry.register(NoContent);
+        let mut scene = Scene::new();
+        content.paint(&mut scene, Rect::new(0.0, 0.0, 1.0, 1.0), 1.0);
+        let old = content.slot.lock().unwrap().image.clone();
+
+        content.paint(&mut scene, Rect::new(0.0, 0.0, 20.0, 10.0), 1.0);
+        let new = content.slot.lock().unwrap().image.clone();
+        assert_ne!(old.data.id(), new.data.id());
+        assert_eq!((new.width, new.height), (20, 10));
+
+        for _ in 0..FRAMES_IN_FLIGHT {
+            let ids: Vec<u64> = content
+                .slot
+                .lock()
+                .unwrap()
+                .images_in_use()
+                .iter()
+                .map(|image| image.data.id())
+                .collect();
+            assert_eq!(ids, [old.data.id(), new.data.id()]);
+        }
+        let images = content.slot.lock().unwrap().images_in_use();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].data.id(), new.data.id());
+    }
+
+    #[test]
+    fn registry_forgets_dropped_content() {
+        let registry = GpuContentRegistry::new();
+        assert!(!registry.has_live());
+
+        let content = registry.register(NoContent);
+        let clone = content.clone();
+        drop(content);
+        assert!(registry.has_live());
+
+        drop(clone);
+        assert!(!registry.has_live());
+    }
+}
@@ -37,9 +37,44 @@
 //! );
 //! ```
 
+mod backend;
+mod clock;
+mod constraints;
+mod damage;
+mod design;
+mod editor_state;
 mod event;
+mod gpu_content;
+mod lifecycle;
+mod options;
+mod parent;
+mod post_process;
 mod render;
+mod render_thread;
+mod resize;
+mod secondary;
+mod state;
+mod stats;
 mod window;
 
+pub use backend::{
+    BackendFactory, PixelBuffer, RecordedFrame, RecordingRenderer, RenderBackend, SceneLog,
+    WindowTarget,
+};
 pub use baseview::{Size, WindowOpenOptions, WindowScalePolicy};
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use constraints::ResizeConstraints;
+pub use design::{DesignFit, DesignSize};
+pub use editor_state::{EditorState, StateError, StateValue};
+pub use gpu_content::{CustomRenderContext, CustomRenderer, GpuContent, GpuContentRegistry};
+pub use lifecycle::LifecycleHooks;
+pub use masonry::app::WindowSizePolicy;
+pub use options::{MasonryOptions, PanicCallback, ResizeCallback, RetryPolicy};
+pub use parent::ParentWindow;
+pub use post_process::{PostProcess, PostProcessParams};
+pub use render::{ColorSpace, FramePacing, RenderContext, RenderError, RenderOptions, SharedGpu};
+pub use resize::{ResizeGrip, ResizeRequester};
+pub use secondary::WindowSpawner;
+pub use state::InitState;
+pub use stats::FrameStats;
 pub use window::{MasonryWindow, MasonryWindowHandle};
@@ -0,0 +1,95 @@
+//! Lifecycle callbacks for editor windows
+//!
+//! Plugins usually mirror the editor's state on their side (whether it is
+//! open, which scale to report to the host). [`LifecycleHooks`] tells them
+//! when that changes. Callbacks run on the window thread; a panicking
+//! callback is caught and logged.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
+type Hook<T> = Option<Arc<dyn Fn(T) + Send + Sync>>;
+
+/// Callbacks for window lifecycle events, see [`MasonryOptions::lifecycle`]
+///
+/// ```ignore
+/// let hooks = LifecycleHooks::new()
+///     .on_open(|| println!("editor open"))
+///     .on_close(|| println!("editor closed"));
+/// let options = MasonryOptions::default().with_lifecycle(hooks);
+/// ```
+///
+/// [`MasonryOptions::lifecycle`]: crate::MasonryOptions::lifecycle
+#[derive(Clone, Default)]
+pub struct LifecycleHooks {
+    pub(crate) on_open: Hook<()>,
+    pub(crate) on_close: Hook<()>,
+    pub(crate) on_focus_change: Hook<bool>,
+    pub(crate) on_scale_change: Hook<f64>,
+}
+
+impl LifecycleHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Called once the editor has its renderer and widget tree
+    pub fn on_open(mut self, callback: impl Fn() + Send + Sync + 'static) -> Self {
+        self.on_open = Some(Arc::new(move |()| callback()));
+        self
+    }
+
+    /// Called once after the window closed and its GPU resources are released
+    pub fn on_close(mut self, callback: impl Fn() + Send + Sync + 'static) -> Self {
+        self.on_close = Some(Arc::new(move |()| callback()));
+        self
+    }
+
+    /// Called with whether the window gained or lost keyboard focus
+    pub fn on_focus_change(mut self, callback: impl Fn(bool) + Send + Sync + 'static) -> Self {
+        self.on_focus_change = Some(Arc::new(callback));
+        self
+    }
+
+    /// Called with the new system scale factor when it changes
+    pub fn on_scale_change(mut self, callback: impl Fn(f64) + Send + Sync + 'static) -> Self {
+        self.on_scale_change = Some(Arc::new(callback));
+        self
+    }
+
+    pub(crate) fn opened(&self) {
+        call("on_open", &self.on_open, ());
+    }
+
+    pub(crate) fn closed(&self) {
+        call("on_close", &self.on_close, ());
+    }
+
+    pub(crate) fn focus_changed(&self, focused: bool) {
+        call("on_focus_change", &self.on_focus_change, focused);
+    }
+
+    pub(crate) fn scale_changed(&self, scale: f64) {
+        call("on_scale_change", &self.on_scale_change, scale);
+    }
+}
+
+/// Run a hook, if set, without letting a panic reach the host
+fn call<T>(name: &str, hook: &Hook<T>, arg: T) {
+    if let Some(hook) = hook {
+        if panic::catch_unwind(AssertUnwindSafe(|| hook(arg))).is_err() {
+            tracing::error!("Lifecycle hook {} panicked", name);
+        }
+    }
+}
+
+impl std::fmt::Debug for LifecycleHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LifecycleHooks")
+            .field("on_open", &self.on_open.is_some())
+            .field("on_close", &self.on_close.is_some())
+            .field("on_focus_change", &self.on_focus_change.is_some())
+            .field("on_scale_change", &self.on_scale_change.is_some())
+            .finish()
+    }
+}
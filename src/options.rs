@@ -0,0 +1,214 @@
+//! Configuration for masonry windows
+//!
+//! Everything that isn't covered by baseview's `WindowOpenOptions`.
+
+use crate::backend::BackendFactory;
+use crate::clock::{Clock, SystemClock};
+use crate::constraints::ResizeConstraints;
+use crate::design::DesignSize;
+use crate::editor_state::EditorState;
+use crate::lifecycle::LifecycleHooks;
+use crate::render::{RenderContext, RenderOptions};
+use crate::resize::ResizeRequester;
+use crate::secondary::WindowSpawner;
+use crate::state::{MAX_ZOOM, MIN_ZOOM};
+use baseview::Size;
+use masonry::app::WindowSizePolicy;
+use std::sync::Arc;
+use std::time::Duration;
+use vello::peniko::Color;
+
+/// Options controlling how masonry is hosted inside a baseview window
+#[derive(Clone)]
+pub struct MasonryOptions {
+    /// GPU rendering options
+    pub render: RenderOptions,
+    /// Color behind all widgets
+    ///
+    /// Use a translucent color together with [`RenderOptions::transparent`]
+    /// for windows that let the host show through.
+    pub base_color: Color,
+    /// Creates the renderer once the window is open
+    ///
+    /// Defaults to the wgpu/Vello [`RenderContext`].
+    pub backend: BackendFactory,
+    /// Encode and submit frames on a dedicated render thread
+    ///
+    /// Masonry still lays out and paints on the window thread, but Vello
+    /// encoding, GPU submission and presenting no longer block the host's GUI
+    /// thread. Frames are dropped while the render thread is busy.
+    pub render_thread: bool,
+    /// Called with the panic message when widget or render code panics
+    ///
+    /// The panic is caught before it reaches the host and the window switches
+    /// to a crash screen. Use this to tell the plugin (e.g. to log or to offer
+    /// reopening the editor).
+    pub on_panic: Option<PanicCallback>,
+    /// How often to retry creating the renderer before giving up
+    pub init_retry: RetryPolicy,
+    /// Time source for animation frames and pointer event timestamps
+    pub clock: Arc<dyn Clock>,
+    /// Longest animation step passed to masonry in one frame
+    ///
+    /// Hosts may stop driving frames for minutes (hidden editors, modal
+    /// dialogs). Larger gaps are clamped so animations resume where they were
+    /// instead of jumping to their end.
+    pub max_frame_delta: Duration,
+    /// Free the renderer's intermediate textures while the editor is hidden
+    ///
+    /// Saves VRAM for editors that stay closed most of the session, at the
+    /// cost of reallocating them when shown again.
+    pub release_when_hidden: bool,
+    /// Highest frame rate to render at, None renders on every host timer tick
+    ///
+    /// Ticks arriving before the frame budget has elapsed are skipped; input
+    /// is still handled and shows up in the next rendered frame. Useful for
+    /// editors that don't need smooth animation, e.g. 30 for utility plugins.
+    pub max_fps: Option<u32>,
+    /// Who decides the window size
+    ///
+    /// With [`WindowSizePolicy::Content`] the widget tree's measured layout
+    /// sizes the window, and [`MasonryWindowHandle::preferred_size`] reports it
+    /// so the plugin can pass it on to the host.
+    ///
+    /// [`MasonryWindowHandle::preferred_size`]: crate::MasonryWindowHandle::preferred_size
+    pub size_policy: WindowSizePolicy,
+    /// Sizes the editor may be resized to
+    ///
+    /// Resizes to other sizes are corrected to the closest allowed one.
+    pub resize_constraints: ResizeConstraints,
+    /// Lay out at a fixed size and scale the editor to fit the window
+    ///
+    /// Masonry never sees the window size in this mode, so
+    /// [`WindowSizePolicy::Content`] has no effect.
+    pub design_size: Option<DesignSize>,
+    /// Initial user zoom factor, multiplied with the system scale factor
+    ///
+    /// The window opens at its `WindowOpenOptions` size times the zoom, see
+    /// [`initial_zoom`](Self::initial_zoom). Change it later with [`MasonryWindowHandle::set_zoom`]; the window
+    /// grows or shrinks with it.
+    ///
+    /// [`MasonryWindowHandle::set_zoom`]: crate::MasonryWindowHandle::set_zoom
+    pub zoom: f64,
+    /// Zoom with Ctrl+Plus, Ctrl+Minus and Ctrl+0 (Cmd on macOS)
+    pub zoom_shortcuts: bool,
+    /// Secondary windows to open from this editor, see [`WindowSpawner`]
+    pub spawner: WindowSpawner,
+    /// Resize requests from widgets (e.g. a [`ResizeGrip`](crate::ResizeGrip))
+    pub resize_requests: ResizeRequester,
+    /// Called with the new logical size after a resize request was applied
+    ///
+    /// Forward it to the host (CLAP's `request_resize`, VST3's
+    /// `IPlugFrame::resizeView`) so the parent window follows.
+    pub on_resize_request: Option<ResizeCallback>,
+    /// Callbacks for opening, closing, focus and scale changes
+    pub lifecycle: LifecycleHooks,
+    /// Editor state kept across open/close cycles, see [`EditorState`]
+    ///
+    /// The window restores its zoom factor from here, overriding `zoom`.
+    /// Pass the same store every time the editor is opened.
+    pub editor_state: EditorState,
+}
+
+/// Retry policy for renderer initialization
+///
+/// Adapter or surface creation can fail transiently while the host is still
+/// setting up its window, but retrying every frame forever is expensive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one
+    pub max_attempts: u32,
+    /// Time to wait between attempts
+    pub delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            delay: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Callback receiving the message of a caught panic
+pub type PanicCallback = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Callback receiving the size the editor resized itself to
+pub type ResizeCallback = Arc<dyn Fn(Size) + Send + Sync>;
+
+impl MasonryOptions {
+    /// Use a different rendering backend
+    pub fn with_backend(mut self, backend: BackendFactory) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Zoom factor a window opened with these options starts at
+    ///
+    /// The zoom saved in [`editor_state`](Self::editor_state) if there is
+    /// one, [`zoom`](Self::zoom) otherwise. Multiply the editor's base size
+    /// by it to answer the host's size query before opening.
+    pub fn initial_zoom(&self) -> f64 {
+        self.editor_state
+            .get_float(EditorState::ZOOM_KEY)
+            .unwrap_or(self.zoom)
+            .clamp(MIN_ZOOM, MAX_ZOOM)
+    }
+
+    /// Get notified about the window's lifecycle
+    pub fn with_lifecycle(mut self, lifecycle: LifecycleHooks) -> Self {
+        self.lifecycle = lifecycle;
+        self
+    }
+}
+
+impl Default for MasonryOptions {
+    fn default() -> Self {
+        Self {
+            render: RenderOptions::default(),
+            base_color: Color::from_rgba8(30, 30, 35, 255), // Dark background
+            backend: RenderContext::factory(),
+            render_thread: false,
+            on_panic: None,
+            init_retry: RetryPolicy::default(),
+            clock: Arc::new(SystemClock::new()),
+            max_frame_delta: Duration::from_millis(100),
+            release_when_hidden: false,
+            max_fps: None,
+            size_policy: WindowSizePolicy::User,
+            resize_constraints: ResizeConstraints::default(),
+            design_size: None,
+            zoom: 1.0,
+            zoom_shortcuts: false,
+            spawner: WindowSpawner::default(),
+            resize_requests: ResizeRequester::default(),
+            on_resize_request: None,
+            lifecycle: LifecycleHooks::default(),
+            editor_state: EditorState::default(),
+        }
+    }
+}
+
+impl std::fmt::Debug for MasonryOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MasonryOptions")
+            .field("render", &self.render)
+            .field("base_color", &self.base_color)
+            .field("render_thread", &self.render_thread)
+            .field("init_retry", &self.init_retry)
+            .field("max_frame_delta", &self.max_frame_delta)
+            .field("release_when_hidden", &self.release_when_hidden)
+            .field("max_fps", &self.max_fps)
+            .field("size_policy", &self.size_policy)
+            .field("resize_constraints", &self.resize_constraints)
+            .field("design_size", &self.design_size)
+            .field("zoom", &self.zoom)
+            .field("zoom_shortcuts", &self.zoom_shortcuts)
+            .field("spawner", &self.spawner)
+            .field("resize_requests", &self.resize_requests)
+            .field("lifecycle", &self.lifecycle)
+            .field("editor_state", &self.editor_state)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,149 @@
+//! Parent window handles from raw-window-handle 0.6
+//!
+//! Baseview still uses raw-window-handle 0.5, while most plugin frameworks
+//! hand out 0.6 handles. [`ParentWindow`] converts one into the other so it can
+//! be passed to [`MasonryWindow::open_parented`](crate::MasonryWindow::open_parented).
+
+use crate::render::RenderError;
+use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+use vello::wgpu::rwh;
+
+/// A host-provided parent window, converted for baseview
+///
+/// ```ignore
+/// let parent = ParentWindow::from_window_handle(&host_window)?;
+/// MasonryWindow::open_parented(&parent, options, || my_widget());
+/// ```
+pub struct ParentWindow {
+    handle: RawWindowHandle,
+}
+
+impl ParentWindow {
+    /// Convert a raw-window-handle 0.6 window
+    pub fn from_window_handle<P>(parent: &P) -> Result<Self, RenderError>
+    where
+        P: rwh::HasWindowHandle + ?Sized,
+    {
+        let handle = parent
+            .window_handle()
+            .map_err(|_| RenderError::WindowHandle("parent window handle unavailable"))?;
+        Self::from_raw(handle.as_raw())
+    }
+
+    /// Convert a raw-window-handle 0.6 raw handle
+    pub fn from_raw(handle: rwh::RawWindowHandle) -> Result<Self, RenderError> {
+        Ok(Self {
+            handle: convert_parent_handle(handle)?,
+        })
+    }
+}
+
+unsafe impl HasRawWindowHandle for ParentWindow {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        self.handle
+    }
+}
+
+/// Convert raw_window_handle 0.6 window handle to 0.5 format
+fn convert_parent_handle(handle: rwh::RawWindowHandle) -> Result<RawWindowHandle, RenderError> {
+    use raw_window_handle as old;
+    use rwh::RawWindowHandle as New;
+
+    let handle = match handle {
+        #[cfg(target_os = "macos")]
+        New::AppKit(h) => {
+            let mut old_handle = old::AppKitWindowHandle::empty();
+            old_handle.ns_view = h.ns_view.as_ptr();
+            RawWindowHandle::AppKit(old_handle)
+        }
+
+        #[cfg(target_os = "linux")]
+        New::Xlib(h) => {
+            let mut old_handle = old::XlibWindowHandle::empty();
+            old_handle.window = h.window;
+            old_handle.visual_id = h.visual_id;
+            RawWindowHandle::Xlib(old_handle)
+        }
+
+        #[cfg(target_os = "linux")]
+        New::Xcb(h) => {
+            let mut old_handle = old::XcbWindowHandle::empty();
+            old_handle.window = h.window.get();
+            old_handle.visual_id = h.visual_id.map_or(0, |id| id.get());
+            RawWindowHandle::Xcb(old_handle)
+        }
+
+        #[cfg(target_os = "linux")]
+        New::Wayland(h) => {
+            let mut old_handle = old::WaylandWindowHandle::empty();
+            old_handle.surface = h.surface.as_ptr();
+            RawWindowHandle::Wayland(old_handle)
+        }
+
+        #[cfg(target_os = "windows")]
+        New::Win32(h) => {
+            let mut old_handle = old::Win32WindowHandle::empty();
+            old_handle.hwnd = h.hwnd.get() as *mut std::ffi::c_void;
+            old_handle.hinstance = h.hinstance.map_or(std::ptr::null_mut(), |hinstance| {
+                hinstance.get() as *mut std::ffi::c_void
+            });
+            RawWindowHandle::Win32(old_handle)
+        }
+
+        _ => {
+            return Err(RenderError::WindowHandle(
+                "unsupported parent window handle type",
+            ));
+        }
+    };
+    Ok(handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn converts_xlib_handles() {
+        let mut handle = rwh::XlibWindowHandle::new(42);
+        handle.visual_id = 7;
+        let RawWindowHandle::Xlib(old) =
+            convert_parent_handle(rwh::RawWindowHandle::Xlib(handle)).unwrap()
+        else {
+            panic!("expected an Xlib handle");
+        };
+        assert_eq!((old.window, old.visual_id), (42, 7));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn converts_xcb_handles() {
+        let window = std::num::NonZeroU32::new(42).unwrap();
+        let mut handle = rwh::XcbWindowHandle::new(window);
+        let RawWindowHandle::Xcb(old) =
+            convert_parent_handle(rwh::RawWindowHandle::Xcb(handle)).unwrap()
+        else {
+            panic!("expected an Xcb handle");
+        };
+        // No visual means the default one
+        assert_eq!((old.window, old.visual_id), (42, 0));
+
+        handle.visual_id = std::num::NonZeroU32::new(7);
+        let RawWindowHandle::Xcb(old) =
+            convert_parent_handle(rwh::RawWindowHandle::Xcb(handle)).unwrap()
+        else {
+            panic!("expected an Xcb handle");
+        };
+        assert_eq!((old.window, old.visual_id), (42, 7));
+    }
+
+    #[test]
+    fn rejects_unsupported_handles() {
+        let handle = rwh::RawWindowHandle::Web(rwh::WebWindowHandle::new(1));
+        assert!(matches!(
+            ParentWindow::from_raw(handle),
+            Err(RenderError::WindowHandle(_))
+        ));
+    }
+}
@@ -0,0 +1,71 @@
+//! User-supplied post-processing in the blit stage
+//!
+//! The blit from Vello's intermediate texture to the surface runs a fragment
+//! shader anyway, so it's a cheap place for whole-window effects: vintage or
+//! CRT skins, dimming the editor while a modal is open, colorblind simulation.
+
+use std::sync::{Arc, Mutex};
+
+/// WGSL used when no post-process shader is configured
+pub(crate) const DEFAULT_POST_PROCESS: &str = r#"
+    fn post_process(uv: vec2<f32>) -> vec4<f32> {
+        return textureSample(t_texture, s_sampler, uv);
+    }
+"#;
+
+/// A custom fragment stage applied while blitting to the surface
+///
+/// `source` is WGSL defining `fn post_process(uv: vec2<f32>) -> vec4<f32>`.
+/// It is spliced into the blit shader and can use:
+///
+/// - `t_texture` / `s_sampler`: the rendered frame (straight alpha, in the
+///   configured [`ColorSpace`](crate::ColorSpace))
+/// - `globals.resolution`: surface size in physical pixels
+/// - `globals.time`: seconds since the renderer was created
+/// - `globals.params`: four user values, see [`PostProcessParams`]
+///
+/// ```ignore
+/// PostProcess::new(r#"
+///     fn post_process(uv: vec2<f32>) -> vec4<f32> {
+///         let color = textureSample(t_texture, s_sampler, uv);
+///         // params.x = dimming amount
+///         return vec4<f32>(color.rgb * (1.0 - globals.params.x), color.a);
+///     }
+/// "#)
+/// ```
+///
+/// The color space transfer and premultiplication are applied to the result.
+/// If the shader fails to compile, the default blit is used and an error logged.
+#[derive(Clone, Debug)]
+pub struct PostProcess {
+    pub source: String,
+    pub params: PostProcessParams,
+}
+
+impl PostProcess {
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            params: PostProcessParams::default(),
+        }
+    }
+
+    /// Handle for updating `globals.params` while the window is open
+    pub fn params(&self) -> PostProcessParams {
+        self.params.clone()
+    }
+}
+
+/// Shared user values exposed to the shader as `globals.params`
+#[derive(Clone, Debug, Default)]
+pub struct PostProcessParams(Arc<Mutex<[f32; 4]>>);
+
+impl PostProcessParams {
+    pub fn set(&self, params: [f32; 4]) {
+        *self.0.lock().unwrap() = params;
+    }
+
+    pub fn get(&self) -> [f32; 4] {
+        *self.0.lock().unwrap()
+    }
+}
@@ -1,39 +1,257 @@
 //! Rendering pipeline for masonry in baseview
 //!
 //! Sets up wgpu surface and Vello renderer for drawing masonry widgets.
-//! Uses an intermediate texture because Vello uses compute shaders that
-//! can't directly target surface textures.
+//! Vello uses compute shaders, so it can only write into storage textures.
+//! When the surface supports `STORAGE_BINDING` with an `Rgba8Unorm` view we
+//! render straight into it, otherwise we render into an intermediate texture
+//! and blit that to the surface.
 
-use std::sync::Arc;
-use vello::peniko::Color;
+use crate::backend::{BackendFactory, PixelBuffer, RenderBackend, WindowTarget};
+use crate::gpu_content::{CustomRenderContext, GpuContentRegistry};
+use crate::post_process::{PostProcess, PostProcessParams, DEFAULT_POST_PROCESS};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use vello::kurbo::{Affine, Rect};
+use vello::peniko::color::LinearSrgb;
+use vello::peniko::{Color, ImageData};
 use vello::wgpu;
 use vello::{AaConfig, RenderParams, Renderer, RendererOptions, Scene};
 use wgpu::{
-    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
-    BindGroupLayoutEntry, BindingResource, BindingType, BlendState, ColorTargetState,
-    ColorWrites, CompositeAlphaMode, Device, DeviceDescriptor, Features, FragmentState, Instance,
-    InstanceDescriptor, Limits, MultisampleState, PipelineLayoutDescriptor, PresentMode,
-    PrimitiveState, Queue, RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType,
-    SamplerDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages, Surface,
-    SurfaceConfiguration, Texture, TextureDescriptor, TextureDimension, TextureFormat,
-    TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
-    VertexState,
+    Adapter, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendState,
+    ColorTargetState, ColorWrites, CommandEncoder, CompositeAlphaMode, Device, DeviceDescriptor,
+    Features, FragmentState, Instance, InstanceDescriptor, Limits, MultisampleState,
+    PipelineLayoutDescriptor, PresentMode, PrimitiveState, Queue, RenderPipeline,
+    RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages, Surface, SurfaceCapabilities,
+    SurfaceConfiguration, SurfaceTexture, Texture, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor,
+    TextureViewDimension, VertexState,
 };
 
-/// GPU rendering context for Vello with intermediate texture blitting
+/// Texture format Vello renders into
+const TARGET_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+
+/// Color space of the values Vello writes into its target
+///
+/// Vello blends in whatever space the scene colors are expressed in. Masonry
+/// widgets use sRGB colors, so the default matches masonry_winit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// Scene colors and blending are sRGB-encoded (default)
+    #[default]
+    Srgb,
+    /// Scene colors are linear light, so blending happens in linear space
+    ///
+    /// The blit encodes the result to sRGB. Scene colors must be given in linear
+    /// light; the window's base color is converted automatically. Dark gradients
+    /// may band since Vello's target only has 8 bits per channel.
+    LinearLight,
+}
+
+/// How presenting frames is synchronized with the display
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FramePacing {
+    /// Wait for vertical blank when presenting (default)
+    ///
+    /// No tearing, and the display refresh rate bounds the frame rate.
+    #[default]
+    Vsync,
+    /// Present as soon as a frame is ready
+    ///
+    /// Lower latency, but frames are only paced by the host's timer and
+    /// [`MasonryOptions::max_fps`](crate::MasonryOptions::max_fps).
+    Immediate,
+}
+
+impl FramePacing {
+    fn present_mode(self) -> PresentMode {
+        match self {
+            Self::Vsync => PresentMode::AutoVsync,
+            Self::Immediate => PresentMode::AutoNoVsync,
+        }
+    }
+}
+
+/// Options for the GPU rendering pipeline
+#[derive(Clone, Debug, Default)]
+pub struct RenderOptions {
+    /// How rendered colors are interpreted when presenting
+    pub color_space: ColorSpace,
+    /// Keep the alpha channel of the rendered frame so the host compositor can
+    /// show through transparent pixels
+    ///
+    /// Combine with a translucent base color to get rounded corners or drop
+    /// shadows. Whether the result is actually see-through depends on the host
+    /// window and platform compositor.
+    pub transparent: bool,
+    /// Only re-render the parts of the window that changed
+    ///
+    /// Keeps an intermediate texture between frames, so the surface is never
+    /// rendered to directly. Widgets that animate without requesting an
+    /// accessibility update trigger full repaints.
+    pub partial_repaint: bool,
+    /// Custom GPU content widgets can embed in their scenes
+    ///
+    /// While any content is alive, every frame is repainted in full.
+    pub gpu_content: GpuContentRegistry,
+    /// Custom fragment shader run while blitting to the surface
+    ///
+    /// Disables rendering directly to the surface.
+    pub post_process: Option<PostProcess>,
+    /// Presenting strategy, see [`FramePacing`]
+    pub pacing: FramePacing,
+    /// GPU device to share with other windows of the same editor
+    pub shared_gpu: Option<SharedGpu>,
+}
+
+/// A GPU device shared between the windows of one editor
+///
+/// The first window to create its renderer creates the device; windows
+/// opened later with a clone of the same `SharedGpu` reuse it instead of
+/// creating their own.
+#[derive(Clone, Default)]
+pub struct SharedGpu {
+    slot: Arc<Mutex<Option<Gpu>>>,
+}
+
+impl SharedGpu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn get(&self) -> Option<Gpu> {
+        self.slot.lock().unwrap().clone()
+    }
+
+    /// Offer a device for sharing, unless one is shared already
+    fn share(&self, gpu: &Gpu) {
+        self.slot.lock().unwrap().get_or_insert_with(|| gpu.clone());
+    }
+}
+
+impl std::fmt::Debug for SharedGpu {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedGpu")
+            .field("initialized", &self.slot.lock().unwrap().is_some())
+            .finish()
+    }
+}
+
+/// Everything needed to create surfaces and render with one device
+#[derive(Clone)]
+struct Gpu {
+    instance: Instance,
+    adapter: Adapter,
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+}
+
+impl Gpu {
+    /// Create a device that can present to `surface`
+    fn request(instance: &Instance, surface: &Surface<'_>) -> Result<Self, RenderError> {
+        // Get adapter
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::LowPower,
+            compatible_surface: Some(surface),
+            force_fallback_adapter: false,
+        }))
+        .map_err(|e| {
+            tracing::warn!("Adapter request failed: {:?}", e);
+            RenderError::NoAdapter
+        })?;
+
+        // Get device and queue
+        let (device, queue) = pollster::block_on(adapter.request_device(&DeviceDescriptor {
+            required_features: Features::empty(),
+            required_limits: Limits::default(),
+            label: Some("masonry_baseview"),
+            memory_hints: wgpu::MemoryHints::default(),
+            ..Default::default()
+        }))
+        .map_err(|e| RenderError::Device(format!("{:?}", e)))?;
+
+        Ok(Self {
+            instance: instance.clone(),
+            adapter,
+            device: Arc::new(device),
+            queue: Arc::new(queue),
+        })
+    }
+}
+
+/// Encoding applied by the blit shader, must match `TRANSFER` in the WGSL source
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Transfer {
+    /// Copy texels unchanged
+    Identity = 0,
+    /// Decode sRGB to linear (the sRGB surface re-encodes on write)
+    SrgbToLinear = 1,
+    /// Encode linear to sRGB (the surface stores values as-is)
+    LinearToSrgb = 2,
+}
+
+impl Transfer {
+    fn new(color_space: ColorSpace, surface_format: TextureFormat) -> Self {
+        match (color_space, surface_format.is_srgb()) {
+            (ColorSpace::Srgb, false) | (ColorSpace::LinearLight, true) => Self::Identity,
+            (ColorSpace::Srgb, true) => Self::SrgbToLinear,
+            (ColorSpace::LinearLight, false) => Self::LinearToSrgb,
+        }
+    }
+}
+
+/// GPU rendering context for Vello
 pub struct RenderContext {
     pub device: Arc<Device>,
     pub queue: Arc<Queue>,
     pub renderer: Renderer,
     pub surface: Surface<'static>,
     pub surface_config: SurfaceConfiguration,
-    // Intermediate texture for Vello rendering
-    target_texture: Texture,
-    target_view: TextureView,
+    // How rendered frames reach the surface
+    present_path: PresentPath,
+    // Color space scene colors are expressed in
+    color_space: ColorSpace,
+    // Color the blit pass clears to
+    clear_color: wgpu::Color,
+    // Scratch texture for partial repaints, kept while damage stays the same size
+    scratch: Option<ScratchTarget>,
+    // Custom content registered by widgets, and the textures we render it into
+    gpu_content: GpuContentRegistry,
+    content_textures: Vec<(ImageData, Texture)>,
     // Blitting pipeline
     blit_pipeline: RenderPipeline,
     blit_bind_group_layout: BindGroupLayout,
     blit_sampler: Sampler,
+    // Uniforms for post-processing shaders
+    blit_globals: wgpu::Buffer,
+    post_process_params: Option<PostProcessParams>,
+    start_time: Instant,
+}
+
+/// How a Vello frame gets onto the surface
+enum PresentPath {
+    /// Vello writes directly into the surface texture
+    Direct,
+    /// Vello writes into an intermediate texture which is blitted to the surface
+    Blit(BlitTarget),
+}
+
+/// Texture Vello renders damaged regions into before they are copied into the target
+struct ScratchTarget {
+    texture: Texture,
+    view: TextureView,
+    size: (u32, u32),
+}
+
+/// Intermediate texture plus the bind group sampling it
+///
+/// The bind group only depends on the texture view, so it lives as long as
+/// the texture and is rebuilt on resize instead of every frame.
+struct BlitTarget {
+    texture: Texture,
+    view: TextureView,
+    bind_group: BindGroup,
 }
 
 impl RenderContext {
@@ -42,16 +260,26 @@ impl RenderContext {
     /// # Safety
     ///
     /// The window handle must remain valid for the lifetime of this context.
-    pub unsafe fn new<W>(window: &W, width: u32, height: u32) -> Result<Self, RenderError>
+    pub unsafe fn new<W>(
+        window: &W,
+        width: u32,
+        height: u32,
+        options: &RenderOptions,
+    ) -> Result<Self, RenderError>
     where
         W: raw_window_handle::HasRawWindowHandle + raw_window_handle::HasRawDisplayHandle,
     {
         #[allow(unused_imports)]
         use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
-        let instance = Instance::new(&InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            ..Default::default()
-        });
+        // Surfaces must come from the instance that created the shared device
+        let shared = options.shared_gpu.as_ref().and_then(SharedGpu::get);
+        let instance = match &shared {
+            Some(gpu) => gpu.instance.clone(),
+            None => Instance::new(&InstanceDescriptor {
+                backends: wgpu::Backends::all(),
+                ..Default::default()
+            }),
+        };
 
         // Create surface from raw window handle
         // We need to use the raw handles from raw-window-handle 0.5
@@ -60,58 +288,78 @@ impl RenderContext {
 
         let surface = instance
             .create_surface_unsafe(wgpu::SurfaceTargetUnsafe::RawHandle {
-                raw_display_handle: convert_display_handle(raw_display),
-                raw_window_handle: convert_window_handle(raw_window),
+                raw_display_handle: convert_display_handle(raw_display)?,
+                raw_window_handle: convert_window_handle(raw_window)?,
             })
             .map_err(|e| RenderError::Surface(e.to_string()))?;
 
-        // Get adapter
-        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
-            power_preference: wgpu::PowerPreference::LowPower,
-            compatible_surface: Some(&surface),
-            force_fallback_adapter: false,
-        }))
-        .map_err(|e| RenderError::Device(format!("Adapter request failed: {:?}", e)))?;
-
-        // Get device and queue
-        let (device, queue) = pollster::block_on(adapter.request_device(
-            &DeviceDescriptor {
-                required_features: Features::empty(),
-                required_limits: Limits::default(),
-                label: Some("masonry_baseview"),
-                memory_hints: wgpu::MemoryHints::default(),
-                ..Default::default()
-            },
-        ))
-        .map_err(|e| RenderError::Device(format!("{:?}", e)))?;
-
-        let device = Arc::new(device);
-        let queue = Arc::new(queue);
+        let gpu = match shared {
+            Some(gpu) if gpu.adapter.is_surface_supported(&surface) => gpu,
+            shared => {
+                if shared.is_some() {
+                    tracing::warn!(
+                        "Shared GPU can't present to this window, creating a new device"
+                    );
+                }
+                let gpu = Gpu::request(&instance, &surface)?;
+                if let Some(shared_gpu) = &options.shared_gpu {
+                    shared_gpu.share(&gpu);
+                }
+                gpu
+            }
+        };
+        let Gpu {
+            adapter,
+            device,
+            queue,
+            ..
+        } = gpu;
 
         // Configure surface
         let caps = surface.get_capabilities(&adapter);
-        let surface_format = caps
-            .formats
-            .iter()
-            .find(|f| !f.is_srgb())
-            .copied()
-            .unwrap_or(TextureFormat::Bgra8Unorm);
-
-        let alpha_mode = if caps.alpha_modes.contains(&CompositeAlphaMode::PreMultiplied) {
-            CompositeAlphaMode::PreMultiplied
+        let storage_supported = adapter
+            .get_texture_format_features(TARGET_FORMAT)
+            .allowed_usages
+            .contains(TextureUsages::STORAGE_BINDING);
+        let alpha_mode = select_alpha_mode(&caps, options.transparent);
+        // Vello stores straight (un-premultiplied) alpha, so a premultiplied
+        // surface needs the blit to multiply it in
+        let premultiply = options.transparent && alpha_mode == CompositeAlphaMode::PreMultiplied;
+
+        // Writing straight to the surface skips the blit, and with it any encoding,
+        // so it is only correct for sRGB output with straight alpha
+        let direct_format = if storage_supported
+            && options.color_space == ColorSpace::Srgb
+            && !premultiply
+            && !options.partial_repaint
+            && options.post_process.is_none()
+        {
+            direct_surface_format(&caps)
         } else {
-            CompositeAlphaMode::Auto
+            None
         };
+        // Prefer a linear surface; on sRGB-only surfaces the blit's transfer
+        // function compensates for the encoding
+        let surface_format = direct_format
+            .or_else(|| caps.formats.iter().find(|f| !f.is_srgb()).copied())
+            .or_else(|| caps.formats.first().copied())
+            .ok_or_else(|| RenderError::Surface("surface supports no formats".to_string()))?;
 
         let width = width.max(1);
         let height = height.max(1);
 
+        let usage = if direct_format.is_some() {
+            TextureUsages::RENDER_ATTACHMENT | TextureUsages::STORAGE_BINDING
+        } else {
+            TextureUsages::RENDER_ATTACHMENT
+        };
+
         let surface_config = SurfaceConfiguration {
-            usage: TextureUsages::RENDER_ATTACHMENT,
+            usage,
             format: surface_format,
             width,
             height,
-            present_mode: PresentMode::AutoVsync,
+            present_mode: options.pacing.present_mode(),
             alpha_mode,
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
@@ -119,14 +367,43 @@ impl RenderContext {
 
         surface.configure(&device, &surface_config);
 
-        // Create intermediate texture for Vello
-        let target_format = TextureFormat::Rgba8Unorm;
-        let (target_texture, target_view) =
-            create_target_texture(&device, width, height, target_format);
+        // Create blit pipeline, encoding for the surface format we ended up with
+        let transfer = Transfer::new(options.color_space, surface_format);
+        tracing::info!(
+            "Surface format {:?}, color space {:?}, blit transfer {:?}, alpha mode {:?}",
+            surface_format,
+            options.color_space,
+            transfer,
+            alpha_mode
+        );
+        let (blit_pipeline, blit_bind_group_layout, blit_sampler) = create_blit_pipeline(
+            &device,
+            surface_format,
+            transfer,
+            premultiply,
+            options.post_process.as_ref().map(|p| p.source.as_str()),
+        );
+        let blit_globals = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("blit_globals"),
+            size: BLIT_GLOBALS_SIZE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
 
-        // Create blit pipeline
-        let (blit_pipeline, blit_bind_group_layout, blit_sampler) =
-            create_blit_pipeline(&device, surface_format);
+        // Create intermediate texture for Vello if we can't render to the surface
+        let present_path = if direct_format.is_some() {
+            tracing::info!("Rendering directly to surface ({:?})", surface_format);
+            PresentPath::Direct
+        } else {
+            PresentPath::Blit(BlitTarget::new(
+                &device,
+                &blit_bind_group_layout,
+                &blit_sampler,
+                &blit_globals,
+                width,
+                height,
+            ))
+        };
 
         // Create Vello renderer
         let renderer = Renderer::new(
@@ -146,11 +423,22 @@ impl RenderContext {
             renderer,
             surface,
             surface_config,
-            target_texture,
-            target_view,
+            present_path,
+            color_space: options.color_space,
+            clear_color: if options.transparent {
+                wgpu::Color::TRANSPARENT
+            } else {
+                wgpu::Color::BLACK
+            },
+            scratch: None,
+            gpu_content: options.gpu_content.clone(),
+            content_textures: Vec::new(),
             blit_pipeline,
             blit_bind_group_layout,
             blit_sampler,
+            blit_globals,
+            post_process_params: options.post_process.as_ref().map(PostProcess::params),
+            start_time: Instant::now(),
         })
     }
 
@@ -163,67 +451,323 @@ impl RenderContext {
         self.surface_config.height = height;
         self.surface.configure(&self.device, &self.surface_config);
 
-        // Recreate intermediate texture
-        let (target_texture, target_view) =
-            create_target_texture(&self.device, width, height, TextureFormat::Rgba8Unorm);
-        self.target_texture = target_texture;
-        self.target_view = target_view;
+        // Recreate intermediate texture and its bind group
+        self.scratch = None;
+        if let PresentPath::Blit(target) = &mut self.present_path {
+            *target = BlitTarget::new(
+                &self.device,
+                &self.blit_bind_group_layout,
+                &self.blit_sampler,
+                &self.blit_globals,
+                width,
+                height,
+            );
+        }
     }
 
     /// Render a Vello scene to the surface
     pub fn render(&mut self, scene: &Scene, base_color: Color) -> Result<(), RenderError> {
+        self.prepare_gpu_content();
+
         let width = self.surface_config.width;
         let height = self.surface_config.height;
 
-        // Render to intermediate texture
         let render_params = RenderParams {
-            base_color,
+            base_color: self.scene_base_color(base_color),
             width,
             height,
             antialiasing_method: AaConfig::Msaa16,
         };
 
-        self.renderer
-            .render_to_texture(
-                &*self.device,
-                &*self.queue,
-                scene,
-                &self.target_view,
-                &render_params,
-            )
-            .map_err(|e| RenderError::Renderer(format!("{:?}", e)))?;
-
         // Get surface texture
         let surface_texture = self
             .surface
             .get_current_texture()
             .map_err(|e| RenderError::Surface(e.to_string()))?;
 
-        let surface_view = surface_texture
-            .texture
-            .create_view(&TextureViewDescriptor::default());
+        let target = match &self.present_path {
+            PresentPath::Direct => {
+                let surface_view = surface_texture.texture.create_view(&TextureViewDescriptor {
+                    format: Some(TARGET_FORMAT),
+                    ..Default::default()
+                });
 
-        // Blit intermediate texture to surface
-        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
-            label: Some("blit_bind_group"),
-            layout: &self.blit_bind_group_layout,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::TextureView(&self.target_view),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::Sampler(&self.blit_sampler),
-                },
-            ],
-        });
+                {
+                    let _span = tracing::debug_span!("vello").entered();
+                    self.renderer
+                        .render_to_texture(
+                            &*self.device,
+                            &*self.queue,
+                            scene,
+                            &surface_view,
+                            &render_params,
+                        )
+                        .map_err(|e| RenderError::Renderer(format!("{:?}", e)))?;
+                }
 
-        let mut encoder = self
+                let _span = tracing::debug_span!("present").entered();
+                surface_texture.present();
+                return Ok(());
+            }
+            PresentPath::Blit(target) => target,
+        };
+
+        // Render to intermediate texture
+        {
+            let _span = tracing::debug_span!("vello").entered();
+            self.renderer
+                .render_to_texture(
+                    &*self.device,
+                    &*self.queue,
+                    scene,
+                    &target.view,
+                    &render_params,
+                )
+                .map_err(|e| RenderError::Renderer(format!("{:?}", e)))?;
+        }
+
+        let encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("blit_encoder"),
             });
+        self.blit_to_surface(encoder, surface_texture);
+
+        Ok(())
+    }
+
+    /// Render only the damaged part of a scene (physical pixels)
+    ///
+    /// Vello renders the damaged rectangle into a scratch texture which is copied
+    /// into the intermediate texture, so the rest of the previous frame is kept.
+    /// Falls back to a full render when rendering directly to the surface.
+    pub fn render_damaged(
+        &mut self,
+        scene: &Scene,
+        base_color: Color,
+        damage: Rect,
+    ) -> Result<(), RenderError> {
+        if matches!(self.present_path, PresentPath::Direct) {
+            return self.render(scene, base_color);
+        }
+        self.prepare_gpu_content();
+
+        let width = self.surface_config.width;
+        let height = self.surface_config.height;
+        let x0 = damage.x0.floor().clamp(0.0, width as f64) as u32;
+        let y0 = damage.y0.floor().clamp(0.0, height as f64) as u32;
+        let x1 = damage.x1.ceil().clamp(0.0, width as f64) as u32;
+        let y1 = damage.y1.ceil().clamp(0.0, height as f64) as u32;
+        if x1 <= x0 || y1 <= y0 {
+            // Nothing visible changed
+            return Ok(());
+        }
+        let (region_width, region_height) = (x1 - x0, y1 - y0);
+
+        let scratch_fits = self
+            .scratch
+            .as_ref()
+            .is_some_and(|s| s.size == (region_width, region_height));
+        if !scratch_fits {
+            let (texture, view) =
+                create_target_texture(&self.device, region_width, region_height, TARGET_FORMAT);
+            self.scratch = Some(ScratchTarget {
+                texture,
+                view,
+                size: (region_width, region_height),
+            });
+        }
+
+        let mut region = Scene::new();
+        region.append(scene, Some(Affine::translate((-(x0 as f64), -(y0 as f64)))));
+
+        let base_color = self.scene_base_color(base_color);
+        let scratch = self.scratch.as_ref().unwrap();
+        {
+            let _span = tracing::debug_span!("vello").entered();
+            self.renderer
+                .render_to_texture(
+                    &*self.device,
+                    &*self.queue,
+                    &region,
+                    &scratch.view,
+                    &RenderParams {
+                        base_color,
+                        width: region_width,
+                        height: region_height,
+                        antialiasing_method: AaConfig::Msaa16,
+                    },
+                )
+                .map_err(|e| RenderError::Renderer(format!("{:?}", e)))?;
+        }
+
+        let PresentPath::Blit(target) = &self.present_path else {
+            unreachable!("direct rendering is handled above");
+        };
+
+        let surface_texture = self
+            .surface
+            .get_current_texture()
+            .map_err(|e| RenderError::Surface(e.to_string()))?;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("damage_encoder"),
+            });
+        encoder.copy_texture_to_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &scratch.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyTextureInfo {
+                texture: &target.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x: x0, y: y0, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::Extent3d {
+                width: region_width,
+                height: region_height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.blit_to_surface(encoder, surface_texture);
+
+        Ok(())
+    }
+
+    /// Run the custom renderers of all registered GPU content
+    ///
+    /// Each content renders into its own texture, which Vello substitutes for
+    /// the placeholder image the widget drew.
+    fn prepare_gpu_content(&mut self) {
+        let live = self.gpu_content.live();
+        if live.is_empty() && self.content_textures.is_empty() {
+            return;
+        }
+        let _span = tracing::debug_span!("gpu_content").entered();
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("gpu_content_encoder"),
+            });
+        let mut textures = Vec::with_capacity(live.len());
+
+        for slot in &live {
+            let mut slot = slot.lock().unwrap();
+            // Older images stay backed until no queued scene can draw them
+            for image in slot.images_in_use() {
+                let existing = self
+                    .content_textures
+                    .iter()
+                    .position(|(known, _)| known.data.id() == image.data.id());
+                let texture = match existing {
+                    Some(index) => self.content_textures.swap_remove(index).1,
+                    None => {
+                        let texture = self.device.create_texture(&TextureDescriptor {
+                            label: Some("gpu_content"),
+                            size: wgpu::Extent3d {
+                                width: image.width,
+                                height: image.height,
+                                depth_or_array_layers: 1,
+                            },
+                            mip_level_count: 1,
+                            sample_count: 1,
+                            dimension: TextureDimension::D2,
+                            format: TARGET_FORMAT,
+                            usage: TextureUsages::RENDER_ATTACHMENT
+                                | TextureUsages::TEXTURE_BINDING
+                                | TextureUsages::COPY_SRC,
+                            view_formats: &[],
+                        });
+                        self.renderer.override_image(
+                            &image,
+                            Some(wgpu::TexelCopyTextureInfoBase {
+                                texture: texture.clone(),
+                                mip_level: 0,
+                                origin: wgpu::Origin3d::ZERO,
+                                aspect: wgpu::TextureAspect::All,
+                            }),
+                        );
+                        texture
+                    }
+                };
+
+                let view = texture.create_view(&TextureViewDescriptor::default());
+                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("gpu_content_clear"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: wgpu::StoreOp::Store,
+                        },
+                        depth_slice: None,
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+
+                slot.renderer.render(&mut CustomRenderContext {
+                    device: &self.device,
+                    queue: &self.queue,
+                    encoder: &mut encoder,
+                    target: &view,
+                    format: TARGET_FORMAT,
+                    width: image.width,
+                    height: image.height,
+                });
+
+                textures.push((image, texture));
+            }
+        }
+
+        // Content that was dropped, or resized before any queued scene
+        for (image, _) in self.content_textures.drain(..) {
+            self.renderer.override_image(&image, None);
+        }
+        self.content_textures = textures;
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Blit the intermediate texture to the surface, then submit and present
+    ///
+    /// The blit always covers the whole surface: swapchain images don't keep
+    /// their contents between frames, so a scissored blit would show stale pixels.
+    fn blit_to_surface(&self, mut encoder: CommandEncoder, surface_texture: SurfaceTexture) {
+        let PresentPath::Blit(target) = &self.present_path else {
+            return;
+        };
+        let _span = tracing::debug_span!("present").entered();
+
+        let params = self
+            .post_process_params
+            .as_ref()
+            .map(PostProcessParams::get)
+            .unwrap_or_default();
+        let globals = [
+            self.surface_config.width as f32,
+            self.surface_config.height as f32,
+            self.start_time.elapsed().as_secs_f32(),
+            0.0,
+            params[0],
+            params[1],
+            params[2],
+            params[3],
+        ];
+        let bytes: Vec<u8> = globals.iter().flat_map(|v| v.to_le_bytes()).collect();
+        self.queue.write_buffer(&self.blit_globals, 0, &bytes);
+
+        let surface_view = surface_texture
+            .texture
+            .create_view(&TextureViewDescriptor::default());
 
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -232,7 +776,7 @@ impl RenderContext {
                     view: &surface_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        load: wgpu::LoadOp::Clear(self.clear_color),
                         store: wgpu::StoreOp::Store,
                     },
                     depth_slice: None,
@@ -243,15 +787,279 @@ impl RenderContext {
             });
 
             render_pass.set_pipeline(&self.blit_pipeline);
-            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.set_bind_group(0, &target.bind_group, &[]);
             render_pass.draw(0..3, 0..1);
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
         surface_texture.present();
+    }
+}
 
-        Ok(())
+impl RenderContext {
+    /// Factory creating the default wgpu/Vello backend
+    pub fn factory() -> BackendFactory {
+        Arc::new(
+            |target: &WindowTarget, width, height, options: &RenderOptions| {
+                // SAFETY: baseview keeps the window alive for as long as its handler
+                // exists, and the handler owns the backend
+                let ctx = unsafe { RenderContext::new(target, width, height, options)? };
+                Ok(Box::new(ctx) as Box<dyn RenderBackend>)
+            },
+        )
+    }
+
+    /// Render a scene into an offscreen texture and copy it back to the CPU
+    pub fn read_pixels(
+        &mut self,
+        scene: &Scene,
+        base_color: Color,
+    ) -> Result<PixelBuffer, RenderError> {
+        self.prepare_gpu_content();
+
+        let width = self.surface_config.width;
+        let height = self.surface_config.height;
+
+        let texture = self.device.create_texture(&TextureDescriptor {
+            label: Some("readback_target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TARGET_FORMAT,
+            usage: TextureUsages::STORAGE_BINDING | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+
+        self.renderer
+            .render_to_texture(
+                &*self.device,
+                &*self.queue,
+                scene,
+                &view,
+                &RenderParams {
+                    base_color: self.scene_base_color(base_color),
+                    width,
+                    height,
+                    antialiasing_method: AaConfig::Msaa16,
+                },
+            )
+            .map_err(|e| RenderError::Renderer(format!("{:?}", e)))?;
+
+        // Rows in the staging buffer must be aligned to 256 bytes
+        let row_bytes = width * 4;
+        let padded_row_bytes = row_bytes.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback_buffer"),
+            size: (padded_row_bytes * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("readback_encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device
+            .poll(wgpu::PollType::Wait)
+            .map_err(|e| RenderError::Device(e.to_string()))?;
+        receiver
+            .recv()
+            .map_err(|e| RenderError::Device(e.to_string()))?
+            .map_err(|e| RenderError::Device(e.to_string()))?;
+
+        let mut data = Vec::with_capacity((row_bytes * height) as usize);
+        {
+            let mapped = slice.get_mapped_range();
+            for row in mapped.chunks(padded_row_bytes as usize) {
+                data.extend_from_slice(&row[..row_bytes as usize]);
+            }
+        }
+        buffer.unmap();
+
+        Ok(PixelBuffer {
+            width,
+            height,
+            data,
+        })
+    }
+
+    /// Convert masonry's sRGB base color into the color space scenes are rendered in
+    fn scene_base_color(&self, base_color: Color) -> Color {
+        match self.color_space {
+            ColorSpace::Srgb => base_color,
+            ColorSpace::LinearLight => {
+                let linear = base_color.convert::<LinearSrgb>();
+                Color::new(linear.components)
+            }
+        }
+    }
+}
+
+impl RenderBackend for RenderContext {
+    fn resize(&mut self, width: u32, height: u32) {
+        RenderContext::resize(self, width, height);
+    }
+
+    fn render(&mut self, scene: &Scene, base_color: Color) -> Result<(), RenderError> {
+        RenderContext::render(self, scene, base_color)
+    }
+
+    fn render_damaged(
+        &mut self,
+        scene: &Scene,
+        base_color: Color,
+        damage: Rect,
+    ) -> Result<(), RenderError> {
+        RenderContext::render_damaged(self, scene, base_color, damage)
+    }
+
+    fn suspend(&mut self) {
+        // Custom content textures are recreated by the next frame
+        for (image, _) in self.content_textures.drain(..) {
+            self.renderer.override_image(&image, None);
+        }
+
+        // Shrink the intermediate texture instead of dropping it, so the
+        // present path stays valid
+        self.scratch = None;
+        if let PresentPath::Blit(target) = &mut self.present_path {
+            *target = BlitTarget::new(
+                &self.device,
+                &self.blit_bind_group_layout,
+                &self.blit_sampler,
+                &self.blit_globals,
+                1,
+                1,
+            );
+        }
+    }
+
+    fn resume(&mut self) {
+        let width = self.surface_config.width;
+        let height = self.surface_config.height;
+        RenderContext::resize(self, width, height);
+    }
+
+    fn read_pixels(
+        &mut self,
+        scene: &Scene,
+        base_color: Color,
+    ) -> Result<PixelBuffer, RenderError> {
+        RenderContext::read_pixels(self, scene, base_color)
+    }
+}
+
+impl BlitTarget {
+    fn new(
+        device: &Device,
+        layout: &BindGroupLayout,
+        sampler: &Sampler,
+        globals: &wgpu::Buffer,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let (texture, view) = create_target_texture(device, width, height, TARGET_FORMAT);
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("blit_bind_group"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: globals.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            texture,
+            view,
+            bind_group,
+        }
+    }
+}
+
+/// Pick how the compositor should treat the surface's alpha channel
+fn select_alpha_mode(caps: &SurfaceCapabilities, transparent: bool) -> CompositeAlphaMode {
+    if !transparent {
+        return if caps
+            .alpha_modes
+            .contains(&CompositeAlphaMode::PreMultiplied)
+        {
+            CompositeAlphaMode::PreMultiplied
+        } else {
+            CompositeAlphaMode::Auto
+        };
     }
+
+    [
+        CompositeAlphaMode::PreMultiplied,
+        CompositeAlphaMode::PostMultiplied,
+    ]
+    .into_iter()
+    .find(|mode| caps.alpha_modes.contains(mode))
+    .unwrap_or_else(|| {
+        tracing::warn!(
+            "Surface doesn't support transparency (alpha modes {:?})",
+            caps.alpha_modes
+        );
+        CompositeAlphaMode::Auto
+    })
+}
+
+/// Pick a surface format Vello can write to directly, if the surface allows storage writes
+///
+/// Vello needs an `Rgba8Unorm` storage view. sRGB formats can't be bound as
+/// storage textures, so other surfaces go through the blit.
+fn direct_surface_format(caps: &SurfaceCapabilities) -> Option<TextureFormat> {
+    let supported = caps.usages.contains(TextureUsages::STORAGE_BINDING)
+        && caps.formats.contains(&TARGET_FORMAT);
+    supported.then_some(TARGET_FORMAT)
 }
 
 fn create_target_texture(
@@ -271,7 +1079,10 @@ fn create_target_texture(
         sample_count: 1,
         dimension: TextureDimension::D2,
         format,
-        usage: TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+        usage: TextureUsages::STORAGE_BINDING
+            | TextureUsages::TEXTURE_BINDING
+            | TextureUsages::COPY_SRC
+            | TextureUsages::COPY_DST,
         view_formats: &[],
     });
 
@@ -279,44 +1090,81 @@ fn create_target_texture(
     (texture, view)
 }
 
-fn create_blit_pipeline(
-    device: &Device,
-    target_format: TextureFormat,
-) -> (RenderPipeline, BindGroupLayout, Sampler) {
-    // Fullscreen blit shader using oversized triangle technique
-    // Vertices: 0=(-1,-1), 1=(3,-1), 2=(-1,3) - covers entire screen when clipped
-    let shader_source = r#"
-        @group(0) @binding(0) var t_texture: texture_2d<f32>;
-        @group(0) @binding(1) var s_sampler: sampler;
-
-        struct VertexOutput {
-            @builtin(position) position: vec4<f32>,
-            @location(0) tex_coord: vec2<f32>,
-        }
+/// Size of the `Globals` uniform in the blit shader
+const BLIT_GLOBALS_SIZE: u64 = 8 * 4;
 
-        @vertex
-        fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
-            var out: VertexOutput;
-            // Oversized triangle: vertex 0=(-1,-1), 1=(3,-1), 2=(-1,3)
-            let x = f32(i32(vertex_index) / 2) * 4.0 - 1.0;
-            let y = f32(i32(vertex_index) % 2) * 4.0 - 1.0;
-            out.position = vec4<f32>(x, y, 0.0, 1.0);
-            // UV coords: map clip space to texture coords
-            out.tex_coord = vec2<f32>((x + 1.0) * 0.5, (1.0 - y) * 0.5);
-            return out;
-        }
+/// Fullscreen blit shader using oversized triangle technique
+///
+/// The `post_process` function is appended at pipeline creation.
+const BLIT_SHADER: &str = r#"
+    // 0 = identity, 1 = sRGB to linear, 2 = linear to sRGB
+    override TRANSFER: u32 = 0u;
+    // Multiply color by alpha for premultiplied surfaces
+    override PREMULTIPLY: bool = false;
 
-        @fragment
-        fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-            return textureSample(t_texture, s_sampler, in.tex_coord);
-        }
-    "#;
+    struct Globals {
+        resolution: vec2<f32>,
+        time: f32,
+        _padding: f32,
+        params: vec4<f32>,
+    }
 
-    let shader = device.create_shader_module(ShaderModuleDescriptor {
-        label: Some("blit_shader"),
-        source: ShaderSource::Wgsl(shader_source.into()),
-    });
+    @group(0) @binding(0) var t_texture: texture_2d<f32>;
+    @group(0) @binding(1) var s_sampler: sampler;
+    @group(0) @binding(2) var<uniform> globals: Globals;
+
+    struct VertexOutput {
+        @builtin(position) position: vec4<f32>,
+        @location(0) tex_coord: vec2<f32>,
+    }
+
+    @vertex
+    fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+        var out: VertexOutput;
+        // Oversized triangle: vertex 0=(-1,-1), 1=(3,-1), 2=(-1,3)
+        let x = f32(i32(vertex_index) / 2) * 4.0 - 1.0;
+        let y = f32(i32(vertex_index) % 2) * 4.0 - 1.0;
+        out.position = vec4<f32>(x, y, 0.0, 1.0);
+        // UV coords: map clip space to texture coords
+        out.tex_coord = vec2<f32>((x + 1.0) * 0.5, (1.0 - y) * 0.5);
+        return out;
+    }
+
+    fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+        let low = c / 12.92;
+        let high = pow((c + 0.055) / 1.055, vec3<f32>(2.4));
+        return select(high, low, c <= vec3<f32>(0.04045));
+    }
+
+    fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+        let low = c * 12.92;
+        let high = 1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055;
+        return select(high, low, c <= vec3<f32>(0.0031308));
+    }
+
+    @fragment
+    fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+        let color = post_process(in.tex_coord);
+        var rgb = color.rgb;
+        if TRANSFER == 1u {
+            rgb = srgb_to_linear(rgb);
+        } else if TRANSFER == 2u {
+            rgb = linear_to_srgb(rgb);
+        }
+        if PREMULTIPLY {
+            rgb = rgb * color.a;
+        }
+        return vec4<f32>(rgb, color.a);
+    }
+"#;
 
+fn create_blit_pipeline(
+    device: &Device,
+    target_format: TextureFormat,
+    transfer: Transfer,
+    premultiply: bool,
+    post_process: Option<&str>,
+) -> (RenderPipeline, BindGroupLayout, Sampler) {
     let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
         label: Some("blit_bind_group_layout"),
         entries: &[
@@ -336,6 +1184,16 @@ fn create_blit_pipeline(
                 ty: BindingType::Sampler(SamplerBindingType::Filtering),
                 count: None,
             },
+            BindGroupLayoutEntry {
+                binding: 2,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(BLIT_GLOBALS_SIZE),
+                },
+                count: None,
+            },
         ],
     });
 
@@ -345,31 +1203,61 @@ fn create_blit_pipeline(
         push_constant_ranges: &[],
     });
 
-    let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-        label: Some("blit_pipeline"),
-        layout: Some(&pipeline_layout),
-        vertex: VertexState {
-            module: &shader,
-            entry_point: Some("vs_main"),
-            buffers: &[],
-            compilation_options: Default::default(),
-        },
-        fragment: Some(FragmentState {
-            module: &shader,
-            entry_point: Some("fs_main"),
-            targets: &[Some(ColorTargetState {
-                format: target_format,
-                blend: Some(BlendState::REPLACE),
-                write_mask: ColorWrites::ALL,
-            })],
-            compilation_options: Default::default(),
-        }),
-        primitive: PrimitiveState::default(),
-        depth_stencil: None,
-        multisample: MultisampleState::default(),
-        multiview: None,
-        cache: None,
-    });
+    let build = |post_process: &str| {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("blit_shader"),
+            source: ShaderSource::Wgsl(format!("{}\n{}", BLIT_SHADER, post_process).into()),
+        });
+
+        device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("blit_pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(ColorTargetState {
+                    format: target_format,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants: &[
+                        ("TRANSFER", transfer as u32 as f64),
+                        ("PREMULTIPLY", if premultiply { 1.0 } else { 0.0 }),
+                    ],
+                    ..Default::default()
+                },
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        })
+    };
+
+    // A broken user shader must not take the editor down, so catch validation
+    // errors and fall back to the plain blit
+    let pipeline = match post_process {
+        Some(source) => {
+            device.push_error_scope(wgpu::ErrorFilter::Validation);
+            let pipeline = build(source);
+            match pollster::block_on(device.pop_error_scope()) {
+                None => pipeline,
+                Some(e) => {
+                    tracing::error!("Post-process shader failed, using default blit: {}", e);
+                    build(DEFAULT_POST_PROCESS)
+                }
+            }
+        }
+        None => build(DEFAULT_POST_PROCESS),
+    };
 
     let sampler = device.create_sampler(&SamplerDescriptor {
         label: Some("blit_sampler"),
@@ -382,13 +1270,22 @@ fn create_blit_pipeline(
 }
 
 /// Errors that can occur during rendering
-#[derive(Debug)]
-#[allow(dead_code)]
+#[derive(Clone, Debug)]
 pub enum RenderError {
+    /// No GPU adapter can render to this window
     NoAdapter,
+    /// Creating or using the GPU device failed
     Device(String),
+    /// The window surface can't be created or acquired
     Surface(String),
+    /// Vello failed to render
     Renderer(String),
+    /// The backend doesn't support this operation
+    Unsupported(&'static str),
+    /// The host handed us a window handle we can't use
+    WindowHandle(&'static str),
+    /// A thread the window needs couldn't be spawned
+    Thread(String),
 }
 
 impl std::fmt::Display for RenderError {
@@ -398,6 +1295,9 @@ impl std::fmt::Display for RenderError {
             Self::Device(e) => write!(f, "Device error: {}", e),
             Self::Surface(e) => write!(f, "Surface error: {}", e),
             Self::Renderer(e) => write!(f, "Renderer error: {}", e),
+            Self::Unsupported(what) => write!(f, "Unsupported: {}", what),
+            Self::WindowHandle(e) => write!(f, "Window handle error: {}", e),
+            Self::Thread(e) => write!(f, "Thread error: {}", e),
         }
     }
 }
@@ -407,11 +1307,11 @@ impl std::error::Error for RenderError {}
 /// Convert raw_window_handle 0.5 display handle to 0.6 format
 fn convert_display_handle(
     handle: raw_window_handle::RawDisplayHandle,
-) -> wgpu::rwh::RawDisplayHandle {
+) -> Result<wgpu::rwh::RawDisplayHandle, RenderError> {
     use raw_window_handle::RawDisplayHandle as Old;
     use wgpu::rwh::RawDisplayHandle as New;
 
-    match handle {
+    let handle = match handle {
         #[cfg(target_os = "macos")]
         Old::AppKit(_) => New::AppKit(wgpu::rwh::AppKitDisplayHandle::new()),
 
@@ -429,52 +1329,175 @@ fn convert_display_handle(
 
         #[cfg(target_os = "linux")]
         Old::Wayland(h) => New::Wayland(wgpu::rwh::WaylandDisplayHandle::new(
-            std::ptr::NonNull::new(h.display).unwrap(),
+            std::ptr::NonNull::new(h.display)
+                .ok_or(RenderError::WindowHandle("null Wayland display"))?,
         )),
 
         #[cfg(target_os = "windows")]
         Old::Windows(_) => New::Windows(wgpu::rwh::WindowsDisplayHandle::new()),
 
-        _ => panic!("Unsupported display handle type"),
-    }
+        _ => return Err(RenderError::WindowHandle("unsupported display handle type")),
+    };
+    Ok(handle)
 }
 
 /// Convert raw_window_handle 0.5 window handle to 0.6 format
-fn convert_window_handle(handle: raw_window_handle::RawWindowHandle) -> wgpu::rwh::RawWindowHandle {
+fn convert_window_handle(
+    handle: raw_window_handle::RawWindowHandle,
+) -> Result<wgpu::rwh::RawWindowHandle, RenderError> {
     use raw_window_handle::RawWindowHandle as Old;
     use wgpu::rwh::RawWindowHandle as New;
 
-    match handle {
+    let handle = match handle {
         #[cfg(target_os = "macos")]
-        Old::AppKit(h) => {
-            let new_handle = wgpu::rwh::AppKitWindowHandle::new(
-                std::ptr::NonNull::new(h.ns_view).unwrap(),
-            );
-            New::AppKit(new_handle)
-        }
+        Old::AppKit(h) => New::AppKit(wgpu::rwh::AppKitWindowHandle::new(
+            std::ptr::NonNull::new(h.ns_view).ok_or(RenderError::WindowHandle("null NSView"))?,
+        )),
 
         #[cfg(target_os = "linux")]
         Old::Xlib(h) => New::Xlib(wgpu::rwh::XlibWindowHandle::new(h.window)),
 
         #[cfg(target_os = "linux")]
         Old::Xcb(h) => New::Xcb(wgpu::rwh::XcbWindowHandle::new(
-            std::num::NonZeroU32::new(h.window).unwrap(),
+            std::num::NonZeroU32::new(h.window)
+                .ok_or(RenderError::WindowHandle("null XCB window"))?,
         )),
 
         #[cfg(target_os = "linux")]
         Old::Wayland(h) => New::Wayland(wgpu::rwh::WaylandWindowHandle::new(
-            std::ptr::NonNull::new(h.surface).unwrap(),
+            std::ptr::NonNull::new(h.surface)
+                .ok_or(RenderError::WindowHandle("null Wayland surface"))?,
         )),
 
         #[cfg(target_os = "windows")]
         Old::Win32(h) => {
             let mut new_handle = wgpu::rwh::Win32WindowHandle::new(
-                std::num::NonZeroIsize::new(h.hwnd as isize).unwrap(),
+                std::num::NonZeroIsize::new(h.hwnd as isize)
+                    .ok_or(RenderError::WindowHandle("null HWND"))?,
             );
             new_handle.hinstance = std::num::NonZeroIsize::new(h.hinstance as isize);
             New::Win32(new_handle)
         }
 
-        _ => panic!("Unsupported window handle type"),
+        _ => return Err(RenderError::WindowHandle("unsupported window handle type")),
+    };
+    Ok(handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caps(formats: &[TextureFormat], usages: TextureUsages) -> SurfaceCapabilities {
+        SurfaceCapabilities {
+            formats: formats.to_vec(),
+            present_modes: vec![PresentMode::Fifo],
+            alpha_modes: vec![CompositeAlphaMode::Opaque],
+            usages,
+        }
+    }
+
+    #[test]
+    fn direct_format_needs_storage_usage() {
+        let caps = caps(
+            &[TextureFormat::Rgba8Unorm],
+            TextureUsages::RENDER_ATTACHMENT,
+        );
+        assert_eq!(direct_surface_format(&caps), None);
+    }
+
+    #[test]
+    fn direct_format_is_rgba8_unorm() {
+        let usages = TextureUsages::RENDER_ATTACHMENT | TextureUsages::STORAGE_BINDING;
+        let caps = caps(
+            &[TextureFormat::Bgra8Unorm, TextureFormat::Rgba8Unorm],
+            usages,
+        );
+        assert_eq!(
+            direct_surface_format(&caps),
+            Some(TextureFormat::Rgba8Unorm)
+        );
+    }
+
+    #[test]
+    fn srgb_surfaces_are_blitted() {
+        let usages = TextureUsages::RENDER_ATTACHMENT | TextureUsages::STORAGE_BINDING;
+        let caps = caps(
+            &[TextureFormat::Rgba8UnormSrgb, TextureFormat::Bgra8Unorm],
+            usages,
+        );
+        assert_eq!(direct_surface_format(&caps), None);
+    }
+
+    #[test]
+    fn srgb_scenes_are_decoded_only_for_srgb_surfaces() {
+        assert_eq!(
+            Transfer::new(ColorSpace::Srgb, TextureFormat::Bgra8Unorm),
+            Transfer::Identity
+        );
+        assert_eq!(
+            Transfer::new(ColorSpace::Srgb, TextureFormat::Bgra8UnormSrgb),
+            Transfer::SrgbToLinear
+        );
+    }
+
+    #[test]
+    fn linear_scenes_are_encoded_only_for_linear_surfaces() {
+        assert_eq!(
+            Transfer::new(ColorSpace::LinearLight, TextureFormat::Rgba8Unorm),
+            Transfer::LinearToSrgb
+        );
+        assert_eq!(
+            Transfer::new(ColorSpace::LinearLight, TextureFormat::Rgba8UnormSrgb),
+            Transfer::Identity
+        );
+    }
+
+    fn alpha_caps(alpha_modes: &[CompositeAlphaMode]) -> SurfaceCapabilities {
+        SurfaceCapabilities {
+            alpha_modes: alpha_modes.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn opaque_windows_prefer_premultiplied_alpha() {
+        let caps = alpha_caps(&[
+            CompositeAlphaMode::Opaque,
+            CompositeAlphaMode::PreMultiplied,
+        ]);
+        assert_eq!(
+            select_alpha_mode(&caps, false),
+            CompositeAlphaMode::PreMultiplied
+        );
+
+        let caps = alpha_caps(&[CompositeAlphaMode::Opaque]);
+        assert_eq!(select_alpha_mode(&caps, false), CompositeAlphaMode::Auto);
+    }
+
+    #[test]
+    fn transparent_windows_need_a_blending_alpha_mode() {
+        let caps = alpha_caps(&[
+            CompositeAlphaMode::Opaque,
+            CompositeAlphaMode::PostMultiplied,
+            CompositeAlphaMode::PreMultiplied,
+        ]);
+        assert_eq!(
+            select_alpha_mode(&caps, true),
+            CompositeAlphaMode::PreMultiplied
+        );
+
+        let caps = alpha_caps(&[
+            CompositeAlphaMode::Opaque,
+            CompositeAlphaMode::PostMultiplied,
+        ]);
+        assert_eq!(
+            select_alpha_mode(&caps, true),
+            CompositeAlphaMode::PostMultiplied
+        );
+
+        // Without one the window is opaque, with a warning
+        let caps = alpha_caps(&[CompositeAlphaMode::Opaque]);
+        assert_eq!(select_alpha_mode(&caps, true), CompositeAlphaMode::Auto);
     }
 }
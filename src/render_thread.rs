@@ -0,0 +1,184 @@
+//! Dedicated render thread
+//!
+//! Moves Vello encoding and GPU submission off the host's GUI thread. The
+//! window thread still runs masonry (RenderRoot isn't Send) and hands each
+//! finished `Scene` to the render thread, which owns the backend.
+
+use crate::backend::RenderBackend;
+use crate::damage::Damage;
+use crate::stats::FrameTimings;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use vello::peniko::Color;
+use vello::Scene;
+
+enum Command {
+    Render {
+        scene: Scene,
+        base_color: Color,
+        damage: Damage,
+        /// Time the window thread already spent on this frame
+        elapsed: Duration,
+    },
+    Resize {
+        width: u32,
+        height: u32,
+        done: SyncSender<()>,
+    },
+    Suspend,
+    Resume,
+    Shutdown,
+}
+
+/// Outcome of handing a frame to the render thread
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Submit {
+    /// The frame was queued
+    Queued,
+    /// The render thread is still busy with the previous frame, this one was dropped
+    Busy,
+}
+
+/// Handle to the render thread, shuts it down when dropped
+pub struct RenderThread {
+    sender: SyncSender<Command>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RenderThread {
+    /// Move a backend onto a new render thread
+    ///
+    /// Completed frames are recorded in `timings`.
+    pub fn spawn(
+        backend: Box<dyn RenderBackend>,
+        timings: Arc<Mutex<FrameTimings>>,
+    ) -> std::io::Result<Self> {
+        // One frame in flight at most, so we never render stale scenes
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let thread = std::thread::Builder::new()
+            .name("masonry_baseview render".into())
+            .spawn(move || run(backend, receiver, timings))?;
+
+        Ok(Self {
+            sender,
+            thread: Some(thread),
+        })
+    }
+
+    /// Hand a scene to the render thread without blocking
+    ///
+    /// `elapsed` is the time already spent on the frame, for the statistics.
+    pub fn submit(
+        &self,
+        scene: Scene,
+        base_color: Color,
+        damage: Damage,
+        elapsed: Duration,
+    ) -> Submit {
+        match self.sender.try_send(Command::Render {
+            scene,
+            base_color,
+            damage,
+            elapsed,
+        }) {
+            Ok(()) => Submit::Queued,
+            Err(TrySendError::Full(_)) => Submit::Busy,
+            Err(TrySendError::Disconnected(_)) => {
+                tracing::error!("Render thread is gone");
+                Submit::Busy
+            }
+        }
+    }
+
+    /// Whether the render thread is still alive (it stops if the backend panics)
+    pub fn is_running(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_some_and(|thread| !thread.is_finished())
+    }
+
+    /// Resize the backend, waiting until the render thread has applied it
+    ///
+    /// Any frame rendered after this returns uses the new size.
+    pub fn resize(&self, width: u32, height: u32) {
+        let (done, wait) = mpsc::sync_channel(1);
+        if self
+            .sender
+            .send(Command::Resize {
+                width,
+                height,
+                done,
+            })
+            .is_ok()
+        {
+            let _ = wait.recv();
+        }
+    }
+
+    /// Release backend memory while the window is hidden
+    pub fn suspend(&self) {
+        let _ = self.sender.send(Command::Suspend);
+    }
+
+    /// Restore the backend after the window is shown again
+    pub fn resume(&self) {
+        let _ = self.sender.send(Command::Resume);
+    }
+}
+
+impl Drop for RenderThread {
+    fn drop(&mut self) {
+        // The backend must be gone before baseview destroys the window
+        let _ = self.sender.send(Command::Shutdown);
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                tracing::error!("Render thread panicked");
+            }
+        }
+    }
+}
+
+fn run(
+    mut backend: Box<dyn RenderBackend>,
+    receiver: Receiver<Command>,
+    timings: Arc<Mutex<FrameTimings>>,
+) {
+    while let Ok(command) = receiver.recv() {
+        match command {
+            Command::Render {
+                scene,
+                base_color,
+                damage,
+                elapsed,
+            } => {
+                let start = Instant::now();
+                let result = {
+                    let _span = tracing::debug_span!("render").entered();
+                    match damage {
+                        Damage::None => Ok(()),
+                        Damage::Partial(rect) => backend.render_damaged(&scene, base_color, rect),
+                        Damage::Full => backend.render(&scene, base_color),
+                    }
+                };
+                if let Err(e) = result {
+                    tracing::error!("Render error: {}", e);
+                }
+                timings.lock().unwrap().record(elapsed + start.elapsed());
+            }
+            Command::Resize {
+                width,
+                height,
+                done,
+            } => {
+                backend.resize(width, height);
+                let _ = done.send(());
+            }
+            Command::Suspend => backend.suspend(),
+            Command::Resume => backend.resume(),
+            Command::Shutdown => break,
+        }
+    }
+    tracing::info!("Render thread stopped");
+}
@@ -0,0 +1,185 @@
+//! Editor-initiated resizing
+//!
+//! Plugin windows are sized by the host, so growing the editor (e.g. when the
+//! user expands an "advanced" panel) takes two steps: resizing the baseview
+//! window and asking the host to resize its parent window. [`ResizeRequester`]
+//! queues the request for the window thread, which does the first and calls
+//! [`MasonryOptions::on_resize_request`](crate::MasonryOptions::on_resize_request)
+//! so the plugin can do the second. [`ResizeGrip`] drives it from a corner
+//! drag handle.
+
+use baseview::Size as WindowSize;
+use masonry::accesskit::{Node, Role};
+use masonry::core::{
+    AccessCtx, BoxConstraints, ChildrenIds, CursorIcon, EventCtx, LayoutCtx, NoAction, PaintCtx,
+    PointerEvent, PropertiesMut, PropertiesRef, QueryCtx, RegisterCtx, Widget,
+};
+use std::sync::{Arc, Mutex};
+use vello::kurbo::{Affine, Line, Point, Size, Stroke};
+use vello::peniko::Color;
+use vello::Scene;
+
+/// Side length of the grip in logical pixels
+const GRIP_SIZE: f64 = 16.0;
+
+#[derive(Debug)]
+struct Requests {
+    /// Latest size asked for, not yet applied
+    pending: Option<WindowSize>,
+    /// Current logical window size, kept up to date by the window
+    current: WindowSize,
+    /// Current window scale factor
+    scale: f64,
+}
+
+/// Queue for resize requests from widgets or the plugin
+///
+/// Share clones between the widget builder and
+/// [`MasonryOptions::resize_requests`](crate::MasonryOptions::resize_requests).
+/// Requests are clamped to the window's resize constraints before they are
+/// applied on the next frame.
+#[derive(Clone, Debug)]
+pub struct ResizeRequester {
+    requests: Arc<Mutex<Requests>>,
+}
+
+impl Default for ResizeRequester {
+    fn default() -> Self {
+        Self {
+            requests: Arc::new(Mutex::new(Requests {
+                pending: None,
+                current: WindowSize::new(0.0, 0.0),
+                scale: 1.0,
+            })),
+        }
+    }
+}
+
+impl ResizeRequester {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask for a new logical window size, replacing any request not applied yet
+    pub fn request(&self, size: WindowSize) {
+        self.requests.lock().unwrap().pending = Some(size);
+    }
+
+    /// Current logical window size
+    pub fn current_size(&self) -> WindowSize {
+        self.requests.lock().unwrap().current
+    }
+
+    pub(crate) fn take(&self) -> Option<WindowSize> {
+        self.requests.lock().unwrap().pending.take()
+    }
+
+    pub(crate) fn set_current(&self, size: WindowSize, scale: f64) {
+        let mut requests = self.requests.lock().unwrap();
+        requests.current = size;
+        requests.scale = scale;
+    }
+
+    fn scale(&self) -> f64 {
+        self.requests.lock().unwrap().scale
+    }
+}
+
+/// Corner handle that resizes the window when dragged
+///
+/// Place it in the bottom-right corner of the editor, e.g. in a `ZStack`
+/// aligned to the bottom end.
+pub struct ResizeGrip {
+    requester: ResizeRequester,
+    /// Pointer position (physical window coordinates) and window size when the drag started
+    drag_start: Option<(Point, WindowSize)>,
+}
+
+impl ResizeGrip {
+    pub fn new(requester: ResizeRequester) -> Self {
+        Self {
+            requester,
+            drag_start: None,
+        }
+    }
+}
+
+impl Widget for ResizeGrip {
+    type Action = NoAction;
+
+    fn on_pointer_event(
+        &mut self,
+        ctx: &mut EventCtx<'_>,
+        _props: &mut PropertiesMut<'_>,
+        event: &PointerEvent,
+    ) {
+        // Window coordinates stay put while the window grows, local ones don't
+        match event {
+            PointerEvent::Down(e) => {
+                ctx.capture_pointer();
+                let position = Point::new(e.state.position.x, e.state.position.y);
+                self.drag_start = Some((position, self.requester.current_size()));
+            }
+            PointerEvent::Move(e) => {
+                if let Some((start, size)) = self.drag_start {
+                    let scale = self.requester.scale();
+                    let dx = (e.current.position.x - start.x) / scale;
+                    let dy = (e.current.position.y - start.y) / scale;
+                    self.requester.request(WindowSize::new(
+                        (size.width + dx).max(GRIP_SIZE),
+                        (size.height + dy).max(GRIP_SIZE),
+                    ));
+                }
+            }
+            PointerEvent::Up(_) | PointerEvent::Cancel(_) => {
+                self.drag_start = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn register_children(&mut self, _ctx: &mut RegisterCtx<'_>) {}
+
+    fn layout(
+        &mut self,
+        _ctx: &mut LayoutCtx<'_>,
+        _props: &mut PropertiesMut<'_>,
+        bc: &BoxConstraints,
+    ) -> Size {
+        bc.constrain(Size::new(GRIP_SIZE, GRIP_SIZE))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx<'_>, _props: &PropertiesRef<'_>, scene: &mut Scene) {
+        // Three diagonal ridges towards the bottom-right corner
+        let size = ctx.size();
+        let color = Color::from_rgba8(255, 255, 255, 96);
+        for offset in [4.0, 8.0, 12.0] {
+            let line = Line::new(
+                (size.width - offset, size.height - 2.0),
+                (size.width - 2.0, size.height - offset),
+            );
+            scene.stroke(&Stroke::new(1.0), Affine::IDENTITY, color, None, &line);
+        }
+    }
+
+    fn accessibility_role(&self) -> Role {
+        Role::Button
+    }
+
+    fn accessibility(
+        &mut self,
+        _ctx: &mut AccessCtx<'_>,
+        _props: &PropertiesRef<'_>,
+        node: &mut Node,
+    ) {
+        node.set_label("Resize");
+    }
+
+    fn children_ids(&self) -> ChildrenIds {
+        ChildrenIds::new()
+    }
+
+    fn get_cursor(&self, _ctx: &QueryCtx<'_>, _pos: Point) -> CursorIcon {
+        CursorIcon::SeResize
+    }
+}
@@ -0,0 +1,89 @@
+//! Secondary windows opened from an editor
+//!
+//! Detachable panels (mixer, mod matrix, preset browser) open as floating
+//! windows owned by the editor that spawned them. They render with the
+//! editor's GPU device (see [`SharedGpu`](crate::SharedGpu)), but not its
+//! custom GPU content, and are closed together with it. Parameter state is
+//! shared the usual way, by cloning it into the panel's widget builder.
+//!
+//! Masonry keeps fonts per render root, so each window loads its own system
+//! font collection.
+
+use crate::gpu_content::GpuContentRegistry;
+use crate::options::MasonryOptions;
+use crate::render::RenderError;
+use crate::window::{MasonryWindow, MasonryWindowHandle};
+use baseview::WindowOpenOptions;
+use masonry::core::Widget;
+use std::sync::{Arc, Mutex};
+
+/// Opens the window once the editor hands it its options
+pub(crate) type OpenFn =
+    Box<dyn FnOnce(MasonryOptions) -> Result<MasonryWindowHandle, RenderError> + Send>;
+
+/// Queue of windows to open from an editor
+///
+/// Share clones between the widget builder and
+/// [`MasonryOptions::spawner`](crate::MasonryOptions::spawner). Windows are
+/// opened on the editor's next frame, with a copy of its options.
+#[derive(Clone, Default)]
+pub struct WindowSpawner {
+    pending: Arc<Mutex<Vec<OpenFn>>>,
+}
+
+impl WindowSpawner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open a floating window showing the widget from `widget_builder`
+    ///
+    /// See [`MasonryWindow::open_floating`] for platform limitations.
+    pub fn open<B, W>(&self, options: WindowOpenOptions, widget_builder: B)
+    where
+        B: FnOnce() -> W + Send + 'static,
+        W: Widget + 'static,
+    {
+        self.pending
+            .lock()
+            .unwrap()
+            .push(Box::new(move |masonry_options| {
+                MasonryWindow::open_floating(options, masonry_options, widget_builder)
+            }));
+    }
+
+    /// Like [`open`](Self::open), for panels embedding custom GPU content
+    ///
+    /// Panels don't run the editor's custom renderers; register the panel's
+    /// own with `gpu_content` and share it with the widget builder.
+    pub fn open_with_gpu_content<B, W>(
+        &self,
+        options: WindowOpenOptions,
+        gpu_content: GpuContentRegistry,
+        widget_builder: B,
+    ) where
+        B: FnOnce() -> W + Send + 'static,
+        W: Widget + 'static,
+    {
+        self.pending
+            .lock()
+            .unwrap()
+            .push(Box::new(move |mut masonry_options| {
+                masonry_options.render.gpu_content = gpu_content;
+                MasonryWindow::open_floating(options, masonry_options, widget_builder)
+            }));
+    }
+
+    /// Windows requested since the last call
+    pub(crate) fn take(&self) -> Vec<OpenFn> {
+        std::mem::take(&mut *self.pending.lock().unwrap())
+    }
+}
+
+impl std::fmt::Debug for WindowSpawner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WindowSpawner")
+            .field("pending", &self.pending.lock().unwrap().len())
+            .finish()
+    }
+}
@@ -0,0 +1,113 @@
+//! State shared between a window's handler and its [`MasonryWindowHandle`]
+//!
+//! The handler runs on the window thread and publishes what the plugin may
+//! want to know; the handle reads it (and sets a few controls) from wherever
+//! the plugin holds it.
+//!
+//! [`MasonryWindowHandle`]: crate::MasonryWindowHandle
+
+use crate::constraints::ResizeConstraints;
+use crate::options::MasonryOptions;
+use crate::render::RenderError;
+use crate::resize::ResizeRequester;
+use crate::stats::FrameTimings;
+use crate::window::Renderer;
+use baseview::Size;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// Zoom factors the handle and shortcuts are limited to
+pub(crate) const MIN_ZOOM: f64 = 0.25;
+pub(crate) const MAX_ZOOM: f64 = 4.0;
+
+/// Progress of the editor's renderer initialization
+#[derive(Clone, Debug, Default)]
+pub enum InitState {
+    /// Not initialized yet, or waiting to retry after a failure
+    #[default]
+    Pending,
+    /// The renderer is up and frames are being drawn
+    Ready,
+    /// Initialization failed and won't be retried
+    ///
+    /// Show this to the user, their editor will stay blank.
+    Failed(RenderError),
+}
+
+/// Everything shared between the handler and the handle
+pub(crate) struct WindowState {
+    pub(crate) init: Mutex<InitState>,
+    /// Whether the host shows the editor, set through the handle
+    pub(crate) visible: AtomicBool,
+    /// Recent frame times, also written by the render thread
+    pub(crate) timings: Arc<Mutex<FrameTimings>>,
+    /// Logical size the editor wants, the open size until masonry reports one
+    pub(crate) preferred_size: Mutex<Size>,
+    /// The window's resize constraints, for answering the host
+    pub(crate) constraints: ResizeConstraints,
+    /// Resize requests, shared with the window's options
+    pub(crate) resize_requests: ResizeRequester,
+    /// User zoom factor on top of the system scale, set through the handle
+    pub(crate) zoom: Mutex<f64>,
+    /// Set by the handle to close the window from the plugin side
+    pub(crate) close_requested: AtomicBool,
+    /// Set once the window is closed (or its handler is gone)
+    pub(crate) closed: AtomicBool,
+    /// The window's renderer, here so the handle can release it on close
+    renderer: Mutex<Option<Renderer>>,
+}
+
+impl WindowState {
+    /// State for a window opened at `size`, which already includes the zoom
+    pub(crate) fn new(size: Size, options: &MasonryOptions) -> Self {
+        let constraints = options.resize_constraints;
+        Self {
+            init: Mutex::default(),
+            visible: AtomicBool::new(true),
+            timings: Arc::default(),
+            preferred_size: Mutex::new(constraints.adjust_size(size)),
+            constraints,
+            resize_requests: options.resize_requests.clone(),
+            zoom: Mutex::new(options.initial_zoom()),
+            close_requested: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
+            renderer: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn is_visible(&self) -> bool {
+        self.visible.load(Ordering::Relaxed)
+    }
+
+    /// The renderer, still usable after a panic while it was locked
+    pub(crate) fn renderer(&self) -> MutexGuard<'_, Option<Renderer>> {
+        self.renderer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Install a freshly created renderer, unless the window is closing
+    ///
+    /// Checked under the renderer lock, so a renderer finishing its
+    /// initialization can't outlive [`MasonryWindowHandle::close`].
+    ///
+    /// [`MasonryWindowHandle::close`]: crate::MasonryWindowHandle::close
+    pub(crate) fn set_renderer(&self, renderer: Renderer) -> bool {
+        let mut slot = self.renderer();
+        if self.close_requested.load(Ordering::Relaxed) {
+            return false;
+        }
+        *slot = Some(renderer);
+        true
+    }
+
+    pub(crate) fn zoom(&self) -> f64 {
+        *self.zoom.lock().unwrap()
+    }
+
+    pub(crate) fn set_zoom(&self, zoom: f64) {
+        *self.zoom.lock().unwrap() = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    pub(crate) fn set_init(&self, state: InitState) {
+        *self.init.lock().unwrap() = state;
+    }
+}
@@ -0,0 +1,141 @@
+//! Frame timing statistics
+//!
+//! Frame times cover the CPU work spent on a frame: event handling since the
+//! previous frame, masonry's animation and redraw passes, and the backend's
+//! encode, submit and present. With a render thread, both threads' parts are
+//! added up. Time spent waiting on the GPU inside `present` is included, time
+//! the frame sat in the render thread's queue is not.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Number of frames the rolling statistics cover (a few seconds at 60 fps)
+const WINDOW: usize = 300;
+
+/// Rolling frame time statistics for a window
+///
+/// See [`MasonryWindowHandle::frame_stats`](crate::MasonryWindowHandle::frame_stats).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Number of frames the statistics below are computed over
+    pub frames: usize,
+    pub mean: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+    /// Frames dropped since the window opened
+    ///
+    /// Counts frames the render thread was too busy to take and, with
+    /// [`MasonryOptions::max_fps`], frame slots that passed without a frame.
+    /// Without a frame rate cap there is no budget to overrun, so inline
+    /// rendering then always reports 0.
+    ///
+    /// [`MasonryOptions::max_fps`]: crate::MasonryOptions::max_fps
+    pub dropped: u64,
+}
+
+/// Recent frame times, shared between the handler and the render thread
+#[derive(Debug, Default)]
+pub(crate) struct FrameTimings {
+    samples: VecDeque<Duration>,
+    dropped: u64,
+}
+
+impl FrameTimings {
+    pub(crate) fn record(&mut self, frame_time: Duration) {
+        if self.samples.len() == WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(frame_time);
+    }
+
+    pub(crate) fn record_dropped(&mut self, frames: u64) {
+        self.dropped += frames;
+    }
+
+    pub(crate) fn stats(&self) -> FrameStats {
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+
+        let percentile = |p: usize| {
+            // Nearest rank, so p99 over few frames is the slowest one
+            let rank = (sorted.len() * p).div_ceil(100);
+            sorted
+                .get(rank.saturating_sub(1))
+                .copied()
+                .unwrap_or_default()
+        };
+        let total: Duration = sorted.iter().sum();
+
+        FrameStats {
+            frames: sorted.len(),
+            mean: total.checked_div(sorted.len() as u32).unwrap_or_default(),
+            p95: percentile(95),
+            p99: percentile(99),
+            max: sorted.last().copied().unwrap_or_default(),
+            dropped: self.dropped,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timings(millis: impl IntoIterator<Item = u64>) -> FrameTimings {
+        let mut timings = FrameTimings::default();
+        for ms in millis {
+            timings.record(Duration::from_millis(ms));
+        }
+        timings
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(FrameTimings::default().stats(), FrameStats::default());
+    }
+
+    #[test]
+    fn single_frame() {
+        let stats = timings([7]).stats();
+        assert_eq!(stats.frames, 1);
+        assert_eq!(stats.p95, Duration::from_millis(7));
+        assert_eq!(stats.p99, Duration::from_millis(7));
+        assert_eq!(stats.max, Duration::from_millis(7));
+    }
+
+    #[test]
+    fn nearest_rank_over_few_frames() {
+        // Recorded out of order, percentiles work on the sorted samples
+        let stats = timings((1..=10).rev()).stats();
+        assert_eq!(stats.frames, 10);
+        assert_eq!(stats.mean, Duration::from_micros(5500));
+        // Rank ceil(9.5) = 10 and ceil(9.9) = 10: the slowest frame
+        assert_eq!(stats.p95, Duration::from_millis(10));
+        assert_eq!(stats.p99, Duration::from_millis(10));
+
+        let stats = timings(1..=20).stats();
+        assert_eq!(stats.p95, Duration::from_millis(19));
+        assert_eq!(stats.p99, Duration::from_millis(20));
+    }
+
+    #[test]
+    fn nearest_rank_over_full_window() {
+        // Only the last 300 frames (101..=400 ms) count
+        let stats = timings(1..=400).stats();
+        assert_eq!(stats.frames, WINDOW);
+        assert_eq!(stats.mean, Duration::from_micros(250_500));
+        assert_eq!(stats.p95, Duration::from_millis(385));
+        assert_eq!(stats.p99, Duration::from_millis(397));
+        assert_eq!(stats.max, Duration::from_millis(400));
+    }
+
+    #[test]
+    fn dropped_frames_accumulate() {
+        let mut timings = timings([1, 2]);
+        timings.record_dropped(1);
+        timings.record_dropped(3);
+        assert_eq!(timings.stats().dropped, 4);
+        assert_eq!(timings.stats().frames, 2);
+    }
+}
@@ -3,22 +3,177 @@
 //! Provides the main window handler that integrates masonry's RenderRoot
 //! with baseview's window system.
 
-use crate::event::{EventTranslator, MasonryEvent};
-use crate::render::RenderContext;
-use baseview::{Event, EventStatus, Window, WindowHandler, WindowOpenOptions};
-use masonry::app::{RenderRoot, RenderRootOptions, WindowSizePolicy};
-use masonry::core::{NewWidget, Widget, WindowEvent as MasonryWindowEvent};
+use crate::backend::{RenderBackend, WindowTarget};
+use crate::clock::frame_delta;
+use crate::constraints::ResizeConstraints;
+use crate::damage::{Damage, DamageTracker};
+use crate::design::scale_frame;
+use crate::editor_state::EditorState;
+use crate::event::{translate_keyboard_event, EventTranslator, MasonryEvent};
+use crate::gpu_content::GpuContentRegistry;
+use crate::lifecycle::LifecycleHooks;
+use crate::options::MasonryOptions;
+use crate::render::{RenderError, RenderOptions, SharedGpu};
+use crate::render_thread::{RenderThread, Submit};
+use crate::resize::ResizeRequester;
+use crate::secondary::WindowSpawner;
+use crate::state::{InitState, WindowState, MAX_ZOOM, MIN_ZOOM};
+use crate::stats::{FrameStats, FrameTimings};
+use baseview::{Event, EventStatus, Size, Window, WindowHandle, WindowHandler, WindowOpenOptions};
+use keyboard_types::{Key, KeyState, KeyboardEvent, Modifiers};
+use masonry::app::{RenderRoot, RenderRootOptions, RenderRootSignal, WindowSizePolicy};
+use masonry::core::{
+    NewWidget, PointerEvent, TextEvent, Widget, WindowEvent as MasonryWindowEvent,
+};
 use masonry::theme::default_property_set;
+use masonry::widgets::Label;
 use raw_window_handle::HasRawWindowHandle;
-use std::sync::Arc;
-use std::time::Instant;
+use std::any::Any;
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use vello::kurbo::{Affine, Point};
 use vello::peniko::Color;
 use vello::Scene;
 
 /// Handle to a masonry window running in baseview
+///
+/// Dropping the handle of a floating window closes it, since nothing else
+/// could. Parented windows stay open until [`close`](Self::close) is called
+/// or the host destroys their parent.
 pub struct MasonryWindowHandle {
-    // Currently empty - baseview handles are fire-and-forget
-    // In future could add communication channel
+    state: Arc<WindowState>,
+    /// baseview's handle to a parented window
+    window: Option<ParentedWindow>,
+    /// Thread running a floating window's event loop
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MasonryWindowHandle {
+    /// Close the window and release its GPU resources
+    ///
+    /// Call this when the plugin destroys its editor (CLAP's `gui.destroy`),
+    /// on the thread that opened the window. The renderer and its surface
+    /// are released first, then baseview closes the window, so the host may
+    /// destroy the parent window as soon as this returns. For floating
+    /// windows this waits until the window is gone.
+    pub fn close(mut self) {
+        self.close_window();
+    }
+
+    fn close_window(&mut self) {
+        {
+            let mut renderer = self.state.renderer();
+            self.state.close_requested.store(true, Ordering::Relaxed);
+            // Joins the render thread, if any
+            renderer.take();
+        }
+        if let Some(mut window) = self.window.take() {
+            window.0.close();
+        }
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                tracing::error!("Window thread panicked");
+            }
+        }
+    }
+
+    /// Whether the window is still open
+    pub fn is_open(&self) -> bool {
+        !self.state.closed.load(Ordering::Relaxed)
+    }
+
+    /// Whether the renderer is initialized, still pending, or failed for good
+    ///
+    /// On failure the editor stays blank; use the error to tell the user why.
+    pub fn init_state(&self) -> InitState {
+        self.state.init.lock().unwrap().clone()
+    }
+
+    /// Tell the editor whether the host is showing it
+    ///
+    /// Call this from the plugin's show/hide callbacks. While hidden, no
+    /// animation frames are sent and nothing is rendered; with
+    /// [`MasonryOptions::release_when_hidden`] the renderer's intermediate
+    /// textures are freed as well. Animations resume without a jump.
+    pub fn set_visible(&self, visible: bool) {
+        self.state.visible.store(visible, Ordering::Relaxed);
+    }
+
+    /// Frame time statistics over the last few seconds of rendering
+    pub fn frame_stats(&self) -> FrameStats {
+        self.state.timings.lock().unwrap().stats()
+    }
+
+    /// Logical size the editor wants to have
+    ///
+    /// With [`WindowSizePolicy::Content`] this follows the widget tree's
+    /// layout once the window is open; before that, and with
+    /// [`WindowSizePolicy::User`], it is the size the window was opened with.
+    /// Use [`MasonryWindow::measure`] to answer the host before opening.
+    pub fn preferred_size(&self) -> Size {
+        *self.state.preferred_size.lock().unwrap()
+    }
+
+    /// The resize constraints the window was opened with
+    pub fn resize_constraints(&self) -> ResizeConstraints {
+        self.state.constraints
+    }
+
+    /// Closest size to `size` the editor allows, for the host's `adjust_size`
+    pub fn adjust_size(&self, size: Size) -> Size {
+        self.state.constraints.adjust_size(size)
+    }
+
+    /// Ask the editor to resize itself to a logical size
+    ///
+    /// Applied on the window's next frame, within its resize constraints;
+    /// [`MasonryOptions::on_resize_request`] is called with the result.
+    pub fn request_resize(&self, size: Size) {
+        self.state.resize_requests.request(size);
+    }
+
+    /// Set the user zoom factor, e.g. 1.5 for "UI size: 150%"
+    ///
+    /// Multiplies the system scale factor and resizes the window to match,
+    /// see [`request_resize`](Self::request_resize). Clamped to 0.25..=4.0.
+    pub fn set_zoom(&self, zoom: f64) {
+        self.state.set_zoom(zoom);
+    }
+
+    /// Current user zoom factor
+    pub fn zoom(&self) -> f64 {
+        self.state.zoom()
+    }
+
+    /// Whether the editor is marked visible
+    ///
+    /// This only reflects [`set_visible`](Self::set_visible); a minimized
+    /// window pauses rendering on its own without changing it.
+    pub fn is_visible(&self) -> bool {
+        self.state.is_visible()
+    }
+}
+
+/// baseview's handle to a parented window
+struct ParentedWindow(WindowHandle);
+
+// SAFETY: baseview's handle isn't `Send` because closing a window is only
+// safe on the thread that opened it. Plugin APIs create and destroy editors
+// on the host's GUI thread, and `close` documents the requirement.
+unsafe impl Send for ParentedWindow {}
+
+impl Drop for MasonryWindowHandle {
+    fn drop(&mut self) {
+        if self.thread.is_some() {
+            self.close_window();
+        }
+    }
 }
 
 /// Builder for creating masonry windows with deferred widget creation
@@ -29,6 +184,12 @@ impl MasonryWindow {
     ///
     /// This is the primary method for CLAP/VST plugin integration.
     /// The widget_builder closure will be called on the window thread to create the widget.
+    ///
+    /// Uses default options, so nothing is restored from an earlier open. Pass
+    /// the plugin's [`EditorState`] through [`open_parented_with`](Self::open_parented_with)
+    /// to keep state across open/close cycles.
+    ///
+    /// Parents from raw-window-handle 0.6 can be passed through [`ParentWindow`](crate::ParentWindow).
     pub fn open_parented<P, B, W>(
         parent: &P,
         options: WindowOpenOptions,
@@ -39,19 +200,96 @@ impl MasonryWindow {
         B: FnOnce() -> W + Send + 'static,
         W: Widget + 'static,
     {
+        Self::open_parented_with(parent, options, MasonryOptions::default(), widget_builder)
+    }
+
+    /// Open a parented window with custom masonry options
+    ///
+    /// The window opens at `options.size` times the
+    /// [initial zoom](MasonryOptions::initial_zoom), within the resize constraints.
+    pub fn open_parented_with<P, B, W>(
+        parent: &P,
+        mut options: WindowOpenOptions,
+        masonry_options: MasonryOptions,
+        widget_builder: B,
+    ) -> MasonryWindowHandle
+    where
+        P: HasRawWindowHandle,
+        B: FnOnce() -> W + Send + 'static,
+        W: Widget + 'static,
+    {
+        options.size = zoomed_size(options.size, &masonry_options);
         let width = options.size.width;
         let height = options.size.height;
 
         // Wrap the builder in Option so we can take it once
         let builder_cell = std::sync::Mutex::new(Some(widget_builder));
+        let state = Arc::new(WindowState::new(options.size, &masonry_options));
+        let handler_state = state.clone();
 
-        Window::open_parented(parent, options, move |_| {
+        let window = Window::open_parented(parent, options, move |_| {
             // Take the builder out of the mutex - this runs on the window thread
             let builder = builder_cell.lock().unwrap().take().unwrap();
-            MasonryHandler::new(builder, width, height)
+            MasonryHandler::new(builder, masonry_options, handler_state, width, height)
         });
 
-        MasonryWindowHandle {}
+        MasonryWindowHandle {
+            state,
+            window: Some(ParentedWindow(window)),
+            thread: None,
+        }
+    }
+
+    /// Open a top-level window without blocking (for floating plugin editors)
+    ///
+    /// The window runs its own event loop on a new thread. Keep the returned
+    /// handle while the window should stay open, and
+    /// [`close`](MasonryWindowHandle::close) or drop it when the plugin is
+    /// destroyed.
+    ///
+    /// Baseview can't hide windows, retitle them, or mark them transient for
+    /// another window. [`set_visible(false)`](MasonryWindowHandle::set_visible)
+    /// only pauses rendering and leaves the last frame on screen, so close
+    /// the window instead of hiding it. The title stays as opened and the
+    /// host's window manager decides stacking. Not available on macOS, where
+    /// windows must be opened on the main thread.
+    pub fn open_floating<B, W>(
+        mut options: WindowOpenOptions,
+        masonry_options: MasonryOptions,
+        widget_builder: B,
+    ) -> Result<MasonryWindowHandle, RenderError>
+    where
+        B: FnOnce() -> W + Send + 'static,
+        W: Widget + 'static,
+    {
+        if cfg!(target_os = "macos") {
+            return Err(RenderError::Unsupported(
+                "floating windows need the main thread on macOS",
+            ));
+        }
+
+        options.size = zoomed_size(options.size, &masonry_options);
+        let width = options.size.width;
+        let height = options.size.height;
+        let state = Arc::new(WindowState::new(options.size, &masonry_options));
+        let handler_state = state.clone();
+
+        let thread = std::thread::Builder::new()
+            .name("masonry_baseview window".into())
+            .spawn(move || {
+                let builder_cell = std::sync::Mutex::new(Some(widget_builder));
+                Window::open_blocking(options, move |_| {
+                    let builder = builder_cell.lock().unwrap().take().unwrap();
+                    MasonryHandler::new(builder, masonry_options, handler_state, width, height)
+                });
+            })
+            .map_err(|e| RenderError::Thread(e.to_string()))?;
+
+        Ok(MasonryWindowHandle {
+            state,
+            window: None,
+            thread: Some(thread),
+        })
     }
 
     /// Open a standalone window (for testing)
@@ -64,16 +302,68 @@ impl MasonryWindow {
         B: FnOnce() -> W + Send + 'static,
         W: Widget + 'static,
     {
+        Self::open_blocking_with(options, MasonryOptions::default(), widget_builder)
+    }
+
+    /// Open a standalone window with custom masonry options (blocking)
+    pub fn open_blocking_with<B, W>(
+        mut options: WindowOpenOptions,
+        masonry_options: MasonryOptions,
+        widget_builder: B,
+    ) where
+        B: FnOnce() -> W + Send + 'static,
+        W: Widget + 'static,
+    {
+        options.size = zoomed_size(options.size, &masonry_options);
         let width = options.size.width;
         let height = options.size.height;
 
         let builder_cell = std::sync::Mutex::new(Some(widget_builder));
+        let size = options.size;
 
         Window::open_blocking(options, move |_| {
             let builder = builder_cell.lock().unwrap().take().unwrap();
-            MasonryHandler::new(builder, width, height)
+            let state = Arc::new(WindowState::new(size, &masonry_options));
+            MasonryHandler::new(builder, masonry_options, state, width, height)
         });
     }
+
+    /// Measure the natural logical size of a widget tree without opening a window
+    ///
+    /// Lays the widget out as [`WindowSizePolicy::Content`] would, so plugins
+    /// can answer the host's size query before the editor exists. Returns
+    /// None if masonry didn't report a size.
+    pub fn measure<W: Widget + 'static>(widget: W) -> Option<Size> {
+        let measured = Rc::new(Cell::new(None));
+        let sink = measured.clone();
+        let _render_root = RenderRoot::new(
+            NewWidget::new(widget),
+            move |signal| {
+                if let RenderRootSignal::SetSize(size) = signal {
+                    sink.set(Some(size));
+                }
+            },
+            RenderRootOptions {
+                default_properties: Arc::new(default_property_set()),
+                use_system_fonts: true,
+                size_policy: WindowSizePolicy::Content,
+                size: masonry::dpi::PhysicalSize::new(1, 1),
+                scale_factor: 1.0,
+                test_font: None,
+            },
+        );
+        measured
+            .get()
+            .map(|size| Size::new(size.width as f64, size.height as f64))
+    }
+}
+
+/// Logical size to open a window at, including its initial zoom
+fn zoomed_size(size: Size, masonry_options: &MasonryOptions) -> Size {
+    let zoom = masonry_options.initial_zoom();
+    masonry_options
+        .resize_constraints
+        .adjust_size(Size::new(size.width * zoom, size.height * zoom))
 }
 
 /// Internal window handler that bridges baseview to masonry
@@ -86,49 +376,177 @@ struct MasonryHandler<W: Widget + 'static> {
     widget_builder: Option<Box<dyn FnOnce() -> W + Send>>,
     /// The masonry render root (created lazily)
     render_root: Option<RenderRoot>,
-    /// GPU rendering context
-    render_ctx: Option<RenderContext>,
+    /// Window options
+    options: MasonryOptions,
+    /// State shared with the MasonryWindowHandle
+    state: Arc<WindowState>,
+    /// Renderer initialization attempts so far
+    init_attempts: u32,
+    /// Earliest time for the next initialization attempt
+    next_init_attempt: Instant,
+    /// Set once initialization failed for good
+    init_failed: bool,
+    /// Signals masonry emitted, drained after events and frames
+    signals: Receiver<RenderRootSignal>,
+    /// Sending half of `signals`, cloned into each RenderRoot's signal sink
+    signal_sender: Sender<RenderRootSignal>,
     /// Event translator
     event_translator: EventTranslator,
-    /// Current scene
-    scene: Scene,
-    /// Last frame time
-    last_frame: Instant,
+    /// Regions to repaint when partial repaint is enabled
+    damage: DamageTracker,
+    /// Last pointer position (physical pixels)
+    last_pointer: Option<Point>,
+    /// Clock time of the last animation frame, None after a reset
+    last_frame: Option<Duration>,
+    /// Time spent handling events since the last rendered frame
+    event_time: Duration,
+    /// Clock time the next frame is due when the frame rate is capped
+    next_frame: Option<Duration>,
     /// Background color
     base_color: Color,
+    /// Panic message once the editor crashed and shows the crash screen
+    crashed: Option<String>,
+    /// Whether rendering is paused, either by the handle or by occlusion
+    hidden: bool,
+    /// Set while baseview reports a zero-sized window (minimized)
+    occluded: bool,
+    /// Set once the window is closing (or crashed beyond recovery), nothing gets rendered after that
+    closed: bool,
+    /// Set once `on_open` was called
+    opened: bool,
+    /// Set while a text widget has keyboard focus, other keys go back to the host
+    text_input: bool,
+    /// Set once the editor was torn down and `on_close` called
+    torn_down: bool,
     /// Window dimensions
     width: f64,
     height: f64,
+    /// Window scale factor
+    scale: f64,
+    /// User zoom currently applied on top of `scale`
+    zoom: f64,
+    /// Secondary windows this editor opened, closed together with it
+    secondaries: Vec<MasonryWindowHandle>,
+    /// Maps the design size onto the window (physical pixels), if scaling to one
+    design_transform: Option<Affine>,
 }
 
 impl<W: Widget + 'static> MasonryHandler<W> {
-    fn new<B>(widget_builder: B, width: f64, height: f64) -> Self
+    fn new<B>(
+        widget_builder: B,
+        mut options: MasonryOptions,
+        state: Arc<WindowState>,
+        width: f64,
+        height: f64,
+    ) -> Self
     where
         B: FnOnce() -> W + Send + 'static,
     {
+        // Secondary windows reuse the device this window creates
+        options.render.shared_gpu.get_or_insert_with(SharedGpu::new);
+        let (signal_sender, signals) = mpsc::channel();
+        let design_transform = options
+            .design_size
+            .map(|design| design.transform(width, height));
+        // The window opened at its zoomed size already
+        let zoom = state.zoom();
+        let mut event_translator = EventTranslator::new(1.0, options.clock.clone());
+        event_translator.set_zoom(zoom);
+        event_translator.set_design_transform(design_transform.map(|t| t.inverse()));
+        options
+            .resize_requests
+            .set_current(Size::new(width, height), 1.0);
         Self {
             widget_builder: Some(Box::new(widget_builder)),
+            signals,
+            signal_sender,
             render_root: None,
-            render_ctx: None,
-            event_translator: EventTranslator::new(1.0),
-            scene: Scene::new(),
-            last_frame: Instant::now(),
-            base_color: Color::from_rgba8(30, 30, 35, 255), // Dark background
+            event_translator,
+            damage: DamageTracker::new(),
+            last_pointer: None,
+            last_frame: None,
+            next_frame: None,
+            event_time: Duration::ZERO,
+            base_color: options.base_color,
+            options,
+            state,
+            init_attempts: 0,
+            next_init_attempt: Instant::now(),
+            init_failed: false,
+            crashed: None,
+            hidden: false,
+            occluded: false,
+            closed: false,
+            opened: false,
+            text_input: false,
+            torn_down: false,
             width,
             height,
+            scale: 1.0,
+            zoom,
+            secondaries: Vec::new(),
+            design_transform,
         }
     }
 
     fn ensure_initialized(&mut self, window: &mut Window) {
         // Initialize GPU context
-        if self.render_ctx.is_none() {
-            match unsafe { RenderContext::new(window, self.width as u32, self.height as u32) } {
+        if self.state.renderer().is_none() {
+            if self.init_failed || Instant::now() < self.next_init_attempt {
+                return;
+            }
+            match (self.options.backend)(
+                &WindowTarget::of(window),
+                self.width as u32,
+                self.height as u32,
+                &self.options.render,
+            ) {
+                Ok(ctx) if self.options.render_thread => {
+                    match RenderThread::spawn(ctx, self.state.timings.clone()) {
+                        Ok(thread) => {
+                            if !self.state.set_renderer(Renderer::Threaded(thread)) {
+                                return;
+                            }
+                            self.init_attempts = 0;
+                            self.state.set_init(InitState::Ready);
+                            tracing::info!("GPU context initialized on render thread");
+                        }
+                        Err(e) => {
+                            // The backend went down with the thread, render inline from the next frame
+                            tracing::error!("Failed to spawn render thread: {}", e);
+                            self.options.render_thread = false;
+                            return;
+                        }
+                    }
+                }
                 Ok(ctx) => {
-                    self.render_ctx = Some(ctx);
+                    if !self.state.set_renderer(Renderer::Inline(ctx)) {
+                        return;
+                    }
+                    self.init_attempts = 0;
+                    self.state.set_init(InitState::Ready);
                     tracing::info!("GPU context initialized");
                 }
                 Err(e) => {
-                    tracing::error!("Failed to create GPU context: {}", e);
+                    self.init_attempts += 1;
+                    let policy = self.options.init_retry;
+                    if self.init_attempts >= policy.max_attempts {
+                        tracing::error!(
+                            "Failed to create GPU context after {} attempts, giving up: {}",
+                            self.init_attempts,
+                            e
+                        );
+                        self.init_failed = true;
+                        self.state.set_init(InitState::Failed(e));
+                    } else {
+                        tracing::warn!(
+                            "Failed to create GPU context (attempt {}/{}): {}",
+                            self.init_attempts,
+                            policy.max_attempts,
+                            e
+                        );
+                        self.next_init_attempt = Instant::now() + policy.delay;
+                    }
                     return;
                 }
             }
@@ -140,17 +558,9 @@ impl<W: Widget + 'static> MasonryHandler<W> {
                 let widget = builder();
                 let new_widget = NewWidget::new(widget);
 
-                let options = RenderRootOptions {
-                    default_properties: Arc::new(default_property_set()),
-                    use_system_fonts: true,
-                    size_policy: WindowSizePolicy::User,
-                    size: masonry::dpi::PhysicalSize::new(self.width as u32, self.height as u32),
-                    scale_factor: 1.0,
-                    test_font: None,
-                };
-
                 // Create render root with signal sink
-                let render_root = RenderRoot::new(new_widget, |_signal| {}, options);
+                let render_root =
+                    RenderRoot::new(new_widget, self.signal_sink(), self.render_root_options());
                 self.render_root = Some(render_root);
 
                 tracing::info!("Widget tree initialized");
@@ -158,84 +568,682 @@ impl<W: Widget + 'static> MasonryHandler<W> {
         }
     }
 
+    /// Signal sink forwarding into `signals`
+    fn signal_sink(&self) -> impl FnMut(RenderRootSignal) + 'static {
+        let sender = self.signal_sender.clone();
+        move |signal| {
+            let _ = sender.send(signal);
+        }
+    }
+
+    /// Act on the signals masonry emitted since the last call
+    fn handle_signals(&mut self, window: &mut Window) {
+        while let Ok(signal) = self.signals.try_recv() {
+            match signal {
+                RenderRootSignal::SetSize(size) => {
+                    if self.options.design_size.is_some()
+                        || !matches!(self.options.size_policy, WindowSizePolicy::Content)
+                    {
+                        continue;
+                    }
+                    let logical = Size::new(
+                        size.width as f64 / self.scale,
+                        size.height as f64 / self.scale,
+                    );
+                    let logical = self.options.resize_constraints.adjust_size(logical);
+                    *self.state.preferred_size.lock().unwrap() = logical;
+
+                    // The resulting resize event updates the renderer and masonry
+                    if logical.width != self.width || logical.height != self.height {
+                        window.resize(logical);
+                    }
+                }
+                RenderRootSignal::TakeFocus => window.focus(),
+                // A text widget got focus; hosts only send keys to the focused window
+                RenderRootSignal::StartIme => {
+                    self.text_input = true;
+                    window.focus();
+                }
+                RenderRootSignal::EndIme => self.text_input = false,
+                _ => {}
+            }
+        }
+    }
+
+    /// Apply a zoom factor set through the handle or a shortcut
+    ///
+    /// Masonry is rescaled right away; the window follows through a resize
+    /// request so the plugin can tell the host.
+    fn apply_zoom(&mut self) {
+        let zoom = self.state.zoom();
+        if zoom == self.zoom {
+            return;
+        }
+        let factor = zoom / self.zoom;
+        self.zoom = zoom;
+        self.event_translator.set_zoom(zoom);
+        self.options.editor_state.set(EditorState::ZOOM_KEY, zoom);
+
+        if self.options.design_size.is_none() {
+            if let Some(render_root) = &mut self.render_root {
+                let _ =
+                    render_root.handle_window_event(MasonryWindowEvent::Rescale(self.scale * zoom));
+            }
+        }
+        self.damage.damage_all();
+        self.options
+            .resize_requests
+            .request(Size::new(self.width * factor, self.height * factor));
+    }
+
+    /// Handle Ctrl+Plus, Ctrl+Minus and Ctrl+0, returns whether the key was used
+    fn handle_zoom_shortcut(&self, event: &KeyboardEvent) -> bool {
+        if event.state != KeyState::Down
+            || !(event.modifiers.contains(Modifiers::CONTROL)
+                || event.modifiers.contains(Modifiers::META))
+        {
+            return false;
+        }
+        let Key::Character(key) = &event.key else {
+            return false;
+        };
+
+        let zoom = self.state.zoom();
+        let zoom = match key.as_str() {
+            // Plus shares its key with '=' on most layouts
+            "+" | "=" => ZOOM_STEPS
+                .iter()
+                .copied()
+                .find(|step| *step > zoom + f64::EPSILON)
+                .unwrap_or(MAX_ZOOM),
+            "-" => ZOOM_STEPS
+                .iter()
+                .rev()
+                .copied()
+                .find(|step| *step < zoom - f64::EPSILON)
+                .unwrap_or(MIN_ZOOM),
+            "0" => 1.0,
+            _ => return false,
+        };
+        self.state.set_zoom(zoom);
+        true
+    }
+
+    /// Open the secondary windows requested through the spawner
+    fn open_secondaries(&mut self) {
+        self.secondaries.retain(MasonryWindowHandle::is_open);
+        for open in self.options.spawner.take() {
+            match open(self.secondary_options()) {
+                Ok(handle) => self.secondaries.push(handle),
+                Err(e) => tracing::error!("Failed to open secondary window: {}", e),
+            }
+        }
+    }
+
+    /// Options for secondary windows: the same rendering, but their own size handling
+    fn secondary_options(&self) -> MasonryOptions {
+        MasonryOptions {
+            size_policy: WindowSizePolicy::User,
+            resize_constraints: ResizeConstraints::default(),
+            resize_requests: ResizeRequester::default(),
+            on_resize_request: None,
+            design_size: None,
+            zoom: self.zoom,
+            spawner: WindowSpawner::default(),
+            // The plugin's hooks are about its editor, not its panels
+            lifecycle: LifecycleHooks::default(),
+            // The editor's custom renderers draw into the editor only
+            render: RenderOptions {
+                gpu_content: GpuContentRegistry::default(),
+                ..self.options.render.clone()
+            },
+            // Panels keep their own zoom instead of overwriting the editor's
+            editor_state: EditorState::default(),
+            ..self.options.clone()
+        }
+    }
+
+    /// Close the secondary windows, waiting until they are gone
+    fn close_secondaries(&mut self) {
+        for handle in self.secondaries.drain(..) {
+            handle.close();
+        }
+    }
+
+    /// Apply the latest pending resize request and tell the plugin about it
+    fn apply_resize_request(&mut self, window: &mut Window) {
+        let Some(size) = self.options.resize_requests.take() else {
+            return;
+        };
+        let size = self.options.resize_constraints.adjust_size(size);
+        if size.width == self.width && size.height == self.height {
+            return;
+        }
+
+        tracing::debug!(
+            "Resizing editor to {}x{} on request",
+            size.width,
+            size.height
+        );
+        window.resize(size);
+        if let Some(on_resize_request) = &self.options.on_resize_request {
+            let on_resize_request = on_resize_request.clone();
+            if panic::catch_unwind(AssertUnwindSafe(|| on_resize_request(size))).is_err() {
+                tracing::error!("Resize callback panicked");
+            }
+        }
+    }
+
+    /// Ask baseview for the closest allowed size if `size` breaks the constraints
+    ///
+    /// The current size is still used until the corrected resize arrives.
+    fn enforce_constraints(&self, window: &mut Window, size: Size) {
+        let allowed = self.options.resize_constraints.adjust_size(size);
+        // Allow for rounding to physical pixels, or this never settles
+        if (allowed.width - size.width).abs() > 0.5 || (allowed.height - size.height).abs() > 0.5 {
+            tracing::debug!(
+                "Resize to {}x{} breaks the constraints, correcting to {}x{}",
+                size.width,
+                size.height,
+                allowed.width,
+                allowed.height
+            );
+            window.resize(allowed);
+        }
+    }
+
+    fn render_root_options(&self) -> RenderRootOptions {
+        // A design size is laid out as is, the scene gets scaled afterwards
+        let (size, size_policy, scale_factor) = match self.options.design_size {
+            Some(design) => (design.size, WindowSizePolicy::User, 1.0),
+            None => (
+                Size::new(self.width, self.height),
+                self.options.size_policy,
+                self.scale * self.zoom,
+            ),
+        };
+        RenderRootOptions {
+            default_properties: Arc::new(default_property_set()),
+            use_system_fonts: true,
+            size_policy,
+            size: masonry::dpi::PhysicalSize::new(size.width as u32, size.height as u32),
+            scale_factor,
+            test_font: None,
+        }
+    }
+
+    /// Recover from a panic in widget or render code
+    ///
+    /// The widget tree may be left in an inconsistent state, so it is replaced
+    /// with a screen showing the panic message. A second panic stops the editor.
+    fn crash(&mut self, phase: &str, payload: Box<dyn Any + Send>) {
+        let message = panic_message(payload.as_ref());
+        tracing::error!("Editor panicked during {}: {}", phase, message);
+
+        if let Some(on_panic) = &self.options.on_panic {
+            // The plugin's callback must not take the host down either
+            let on_panic = on_panic.clone();
+            let message = message.clone();
+            if panic::catch_unwind(AssertUnwindSafe(|| on_panic(&message))).is_err() {
+                tracing::error!("Panic callback panicked");
+            }
+        }
+
+        if self.crashed.is_some() {
+            tracing::error!("Editor panicked again after crashing, giving up");
+            self.render_root = None;
+            self.state.renderer().take();
+            self.closed = true;
+            return;
+        }
+
+        // The renderer may be broken too if it panicked mid-frame
+        if phase == "render" {
+            self.state.renderer().take();
+            self.state.set_init(InitState::Pending);
+        }
+
+        let text = format!("The editor crashed.\n\n{}", message);
+        let options = self.render_root_options();
+        let sink = self.signal_sink();
+        let crash_root = panic::catch_unwind(AssertUnwindSafe(|| {
+            RenderRoot::new(NewWidget::new(Label::new(text)), sink, options)
+        }));
+        self.widget_builder = None;
+        self.render_root = crash_root.ok();
+        self.text_input = false;
+        self.damage.damage_all();
+        self.crashed = Some(message);
+    }
+
+    /// Pause or resume rendering when the editor is hidden or shown
+    fn set_hidden(&mut self, hidden: bool) {
+        if hidden == self.hidden {
+            return;
+        }
+        self.hidden = hidden;
+
+        if hidden {
+            tracing::debug!("Editor hidden, pausing rendering");
+            if self.options.release_when_hidden {
+                if let Some(renderer) = self.state.renderer().as_mut() {
+                    renderer.suspend();
+                }
+            }
+        } else {
+            tracing::debug!("Editor visible, resuming rendering");
+            if self.options.release_when_hidden {
+                if let Some(renderer) = self.state.renderer().as_mut() {
+                    renderer.resume();
+                }
+            }
+            // Restart the animation clock instead of catching up on the hidden time
+            self.last_frame = None;
+            self.next_frame = None;
+            self.event_time = Duration::ZERO;
+            self.damage.damage_all();
+        }
+    }
+
+    /// Tear the editor down, at most once
+    ///
+    /// GPU resources go first, while the parent window is still valid:
+    /// secondary windows, then the renderer (joining the render thread that
+    /// owns the surface), then the widget tree. `on_close` runs last.
+    fn shutdown(&mut self) {
+        if self.torn_down {
+            return;
+        }
+        self.torn_down = true;
+        self.closed = true;
+        self.close_secondaries();
+        self.state.renderer().take();
+        self.render_root = None;
+        self.state.closed.store(true, Ordering::Relaxed);
+        self.options.lifecycle.closed();
+    }
+
     fn handle_masonry_event(&mut self, event: MasonryEvent) {
+        // Closing must not depend on the editor having initialized
+        if let MasonryEvent::Close = event {
+            self.shutdown();
+            return;
+        }
+
         let Some(render_root) = &mut self.render_root else {
             return;
         };
 
         match event {
             MasonryEvent::Pointer(ptr_event) => {
+                // Hover and press feedback changes the widgets under the old and new position
+                if let Some(position) = self.last_pointer {
+                    self.damage.add_at(position);
+                }
+                if let Some(position) = pointer_event_position(&ptr_event) {
+                    self.damage.add_at(position);
+                    self.last_pointer = Some(position);
+                }
                 let _ = render_root.handle_pointer_event(ptr_event);
             }
-            MasonryEvent::Keyboard(_kb_event) => {
-                // TODO: Implement keyboard event handling
-                // Would need to convert keyboard_types to masonry's TextEvent
+            MasonryEvent::Keyboard(kb_event) => {
+                let event = translate_keyboard_event(&kb_event);
+                let _ = render_root.handle_text_event(TextEvent::Keyboard(event));
+                // The caret and selection aren't part of the accessibility tree
+                self.damage.damage_all();
             }
-            MasonryEvent::Resize { width, height, scale } => {
+            MasonryEvent::Resize {
+                width,
+                height,
+                scale,
+            } => {
+                // Minimized windows shrink to nothing; keep the last size and stop rendering
+                self.occluded = width < 1.0 || height < 1.0;
+                if self.occluded {
+                    return;
+                }
+
+                if scale != self.scale {
+                    self.options.lifecycle.scale_changed(scale);
+                }
                 self.width = width / scale;
                 self.height = height / scale;
+                self.scale = scale;
                 self.event_translator.set_scale_factor(scale);
+                self.options
+                    .resize_requests
+                    .set_current(Size::new(self.width, self.height), scale);
+
+                if let Some(renderer) = self.state.renderer().as_mut() {
+                    renderer.resize(width as u32, height as u32);
+                }
+                self.damage.damage_all();
 
-                if let Some(ctx) = &mut self.render_ctx {
-                    ctx.resize(width as u32, height as u32);
+                // Masonry keeps the design size, only the scene's scale changes
+                if let Some(design) = self.options.design_size {
+                    let transform = design.transform(width, height);
+                    self.design_transform = Some(transform);
+                    self.event_translator
+                        .set_design_transform(Some(transform.inverse()));
+                    return;
                 }
 
                 // Send resize and rescale events
                 let _ = render_root.handle_window_event(MasonryWindowEvent::Resize(
                     masonry::dpi::PhysicalSize::new(width as u32, height as u32),
                 ));
-                let _ = render_root.handle_window_event(MasonryWindowEvent::Rescale(scale));
+                let _ =
+                    render_root.handle_window_event(MasonryWindowEvent::Rescale(scale * self.zoom));
             }
-            MasonryEvent::Focus(_focused) => {
-                // Masonry doesn't have focus events in WindowEvent
-                // Focus tracking is handled internally by pointer/keyboard events
+            MasonryEvent::Focus(focused) => {
+                // Lets text widgets stop blinking their caret while the window is inactive
+                let _ = render_root.handle_text_event(TextEvent::WindowFocusChange(focused));
+                self.damage.damage_all();
+                self.options.lifecycle.focus_changed(focused);
             }
-            MasonryEvent::Close => {
-                // Window closing - cleanup handled by drop
+            MasonryEvent::Close => unreachable!("handled above"),
+        }
+    }
+
+    /// Whether a frame should be rendered on this tick, given `max_fps`
+    fn frame_due(&mut self) -> bool {
+        let Some(max_fps) = self.options.max_fps else {
+            return true;
+        };
+        let budget = Duration::from_secs_f64(1.0 / f64::from(max_fps.max(1)));
+        let now = self.options.clock.now();
+
+        // Host timers jitter, so accept ticks arriving slightly early
+        if let Some(next) = self.next_frame {
+            if now + budget / 4 < next {
+                return false;
             }
         }
+
+        // Keep a steady cadence, but don't try to catch up after a stall
+        self.next_frame = Some(match self.next_frame {
+            Some(next) if now < next + budget => next + budget,
+            Some(next) => {
+                // Every whole frame slot that went by without a frame was dropped
+                let missed = (now - next).as_secs_f64() / budget.as_secs_f64();
+                self.state
+                    .timings
+                    .lock()
+                    .unwrap()
+                    .record_dropped(missed as u64);
+                now + budget
+            }
+            None => now + budget,
+        });
+        true
     }
 
     fn render_frame(&mut self) {
-        // Skip rendering entirely until both render_root and render_ctx are initialized
+        // Skip rendering entirely until both render_root and renderer are initialized
         // This prevents showing garbage/triangle on the first frame
-        if self.render_root.is_none() || self.render_ctx.is_none() {
+        let mut slot = self.state.renderer();
+        let (Some(render_root), Some(renderer)) = (self.render_root.as_mut(), slot.as_mut()) else {
             return;
-        }
-        let render_root = self.render_root.as_mut().unwrap();
-        let render_ctx = self.render_ctx.as_mut().unwrap();
+        };
+        let start = Instant::now();
 
-        // Calculate animation delta
-        let now = Instant::now();
-        let dt = now.duration_since(self.last_frame);
-        self.last_frame = now;
+        let now = self.options.clock.now();
+        let dt = frame_delta(self.last_frame, now, self.options.max_frame_delta);
+        self.last_frame = Some(now);
 
         // Send animation frame event
-        let _ = render_root.handle_window_event(MasonryWindowEvent::AnimFrame(dt));
+        {
+            let _span = tracing::debug_span!("anim_frame").entered();
+            let _ = render_root.handle_window_event(MasonryWindowEvent::AnimFrame(dt));
+        }
 
         // Get the rendered scene from masonry
-        let (scene, _accessibility) = render_root.redraw();
-        self.scene = scene;
+        let (scene, tree_update) = {
+            let _span = tracing::debug_span!("redraw").entered();
+            render_root.redraw()
+        };
+
+        let damage = if self.options.render.partial_repaint {
+            self.damage.apply(&tree_update);
+            // Animations usually repaint without touching the accessibility tree
+            if render_root.needs_anim() {
+                self.damage.damage_all();
+            }
+            // Custom GPU content redraws every frame, wherever its widget put it
+            if self.options.render.gpu_content.has_live() {
+                self.damage.damage_all();
+            }
+            self.damage.take()
+        } else {
+            Damage::Full
+        };
+
+        if damage == Damage::None {
+            return;
+        }
+
+        // Scale a design-size scene (and its damage) to the window
+        let (scene, damage) = match self.design_transform {
+            Some(transform) => scale_frame(scene, damage, transform),
+            None => (scene, damage),
+        };
 
         // Render to surface
-        if let Err(e) = render_ctx.render(&self.scene, self.base_color) {
-            tracing::error!("Render error: {}", e);
+        let elapsed = std::mem::take(&mut self.event_time) + start.elapsed();
+        if !renderer.render(scene, self.base_color, damage, elapsed, &self.state.timings) {
+            // The frame was dropped, so its damage has to be repainted next time
+            self.damage.damage_all();
+        }
+    }
+}
+
+/// Zoom factors Ctrl+Plus and Ctrl+Minus step through
+const ZOOM_STEPS: [f64; 8] = [0.5, 0.75, 1.0, 1.25, 1.5, 1.75, 2.0, 3.0];
+
+/// Where frames get rendered
+pub(crate) enum Renderer {
+    /// On the window thread, inside `on_frame`
+    Inline(Box<dyn RenderBackend>),
+    /// Handed off to a dedicated render thread
+    Threaded(RenderThread),
+}
+
+impl Renderer {
+    fn resize(&mut self, width: u32, height: u32) {
+        match self {
+            Self::Inline(backend) => backend.resize(width, height),
+            Self::Threaded(thread) => thread.resize(width, height),
+        }
+    }
+
+    fn suspend(&mut self) {
+        match self {
+            Self::Inline(backend) => backend.suspend(),
+            Self::Threaded(thread) => thread.suspend(),
+        }
+    }
+
+    fn resume(&mut self) {
+        match self {
+            Self::Inline(backend) => backend.resume(),
+            Self::Threaded(thread) => thread.resume(),
+        }
+    }
+
+    /// Render a frame, returns false if it was dropped
+    ///
+    /// `elapsed` is the time already spent on the frame; the total is
+    /// recorded in `timings` once the frame is presented.
+    fn render(
+        &mut self,
+        scene: Scene,
+        base_color: Color,
+        damage: Damage,
+        elapsed: Duration,
+        timings: &Mutex<FrameTimings>,
+    ) -> bool {
+        match self {
+            Self::Inline(backend) => {
+                let start = Instant::now();
+                let result = {
+                    let _span = tracing::debug_span!("render").entered();
+                    match damage {
+                        Damage::None => Ok(()),
+                        Damage::Partial(rect) => backend.render_damaged(&scene, base_color, rect),
+                        Damage::Full => backend.render(&scene, base_color),
+                    }
+                };
+                if let Err(e) = result {
+                    tracing::error!("Render error: {}", e);
+                }
+                timings.lock().unwrap().record(elapsed + start.elapsed());
+                true
+            }
+            Self::Threaded(thread) => {
+                let queued = thread.submit(scene, base_color, damage, elapsed) == Submit::Queued;
+                if !queued {
+                    timings.lock().unwrap().record_dropped(1);
+                }
+                queued
+            }
         }
     }
 }
 
+/// Extract the message from a panic payload
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Physical position carried by a pointer event, if any
+fn pointer_event_position(event: &PointerEvent) -> Option<Point> {
+    let position = match event {
+        PointerEvent::Down(e) | PointerEvent::Up(e) => e.state.position,
+        PointerEvent::Move(e) => e.current.position,
+        PointerEvent::Scroll(e) => e.state.position,
+        _ => return None,
+    };
+    Some(Point::new(position.x, position.y))
+}
+
+impl<W: Widget + 'static> Drop for MasonryHandler<W> {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
 impl<W: Widget + 'static> WindowHandler for MasonryHandler<W> {
     fn on_frame(&mut self, window: &mut Window) {
-        self.ensure_initialized(window);
-        self.render_frame();
+        if self.closed {
+            return;
+        }
+
+        if self.state.close_requested.load(Ordering::Relaxed) {
+            // Release the GPU before the window goes away
+            self.shutdown();
+            window.close();
+            return;
+        }
+
+        if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| self.apply_zoom())) {
+            self.crash("event handling", payload);
+            return;
+        }
+        self.apply_resize_request(window);
+        self.open_secondaries();
+
+        // No animations or GPU work while nobody can see the editor
+        self.set_hidden(!self.state.is_visible() || self.occluded);
+        if self.hidden {
+            return;
+        }
+
+        // Never let a panic unwind into the host
+        if let Err(payload) =
+            panic::catch_unwind(AssertUnwindSafe(|| self.ensure_initialized(window)))
+        {
+            self.crash("initialization", payload);
+            return;
+        }
+        if !self.opened && self.render_root.is_some() && self.state.renderer().is_some() {
+            self.opened = true;
+            self.options.lifecycle.opened();
+        }
+        if self.frame_due() {
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| self.render_frame())) {
+                self.crash("render", payload);
+                return;
+            }
+        }
+        self.handle_signals(window);
+
+        let render_thread_stopped = matches!(
+            &*self.state.renderer(),
+            Some(Renderer::Threaded(thread)) if !thread.is_running()
+        );
+        if render_thread_stopped {
+            self.state.renderer().take();
+            self.state.set_init(InitState::Pending);
+            self.crash("render", Box::new("render thread stopped unexpectedly"));
+        }
     }
 
-    fn on_event(&mut self, _window: &mut Window, event: Event) -> EventStatus {
-        if let Some(masonry_event) = self.event_translator.translate(&event) {
-            self.handle_masonry_event(masonry_event);
-            EventStatus::Captured
-        } else {
-            EventStatus::Ignored
+    fn on_event(&mut self, window: &mut Window, event: Event) -> EventStatus {
+        let Some(masonry_event) = self.event_translator.translate(&event) else {
+            return EventStatus::Ignored;
+        };
+
+        // The crash screen isn't interactive, but resizes and closing still matter
+        if self.crashed.is_some()
+            && !matches!(
+                masonry_event,
+                MasonryEvent::Resize { .. } | MasonryEvent::Close
+            )
+        {
+            return EventStatus::Ignored;
+        }
+
+        if let MasonryEvent::Resize {
+            width,
+            height,
+            scale,
+        } = masonry_event
+        {
+            // Minimizing reports a 0x0 size, which is nothing to correct
+            if width >= 1.0 && height >= 1.0 {
+                self.enforce_constraints(window, Size::new(width / scale, height / scale));
+            }
+        }
+
+        if let MasonryEvent::Keyboard(kb_event) = &masonry_event {
+            if self.options.zoom_shortcuts && self.handle_zoom_shortcut(kb_event) {
+                return EventStatus::Captured;
+            }
+        }
+
+        // Without a focused text widget, keys are the host's shortcuts
+        if matches!(masonry_event, MasonryEvent::Keyboard(_)) && !self.text_input {
+            return EventStatus::Ignored;
+        }
+
+        let start = Instant::now();
+        let result = {
+            let _span = tracing::debug_span!("event").entered();
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                self.handle_masonry_event(masonry_event)
+            }))
+        };
+        self.event_time += start.elapsed();
+        if let Err(payload) = result {
+            self.crash("event handling", payload);
         }
+        self.handle_signals(window);
+        EventStatus::Captured
     }
 }